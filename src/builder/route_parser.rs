@@ -8,7 +8,7 @@ use crate::builder::entities::GramRoute;
 /**
 # Build the route file from yml input
 
-- groups can only be build by the route builder
+- groups can be build with `Router::group` or with the route builder
 - the builder parses a yml file and create a new route file. this file contains a function which returns a router (to use it later).
 - all routes in a route shares the same middleware and the same prefix
 - nested groups are also possible
@@ -5,21 +5,49 @@
 //
 #[cfg(feature = "route_builder")]
 mod builder;
+#[cfg(unix)]
+mod listen_fd;
+mod reload;
 mod router;
+mod server;
 pub mod service;
+#[cfg(feature = "tls")]
+mod tls;
+#[cfg(unix)]
+mod unix;
 
-use std::convert::Infallible;
+use std::future::Future;
 use std::net::SocketAddr;
 use std::sync::Arc;
+use std::time::Duration;
 
-use hyper::service::{make_service_fn, service_fn};
-use hyper::Server;
-pub use router::route::{r, RouteParams};
+use hyper::body::HttpBody;
+use hyper::header::{HeaderValue, ALLOW, CONTENT_LENGTH, HOST, LOCATION};
+use hyper::{Method, StatusCode};
+#[cfg(unix)]
+pub use listen_fd::listen_fds;
+pub use matchit::InsertError;
+pub use reload::ReloadableRouter;
+pub use router::group::RouteGroup;
+pub use router::guard;
+pub use router::guard::Guard;
+pub use router::host::Subdomain;
+pub use router::named_route::RouteHandle;
+pub use router::path::TrailingSlash;
+pub use router::route::{r, MatchedRoute, RouteParams, RouteService};
+pub use router::route_error::{ParamError, RouteError, RouteErrorKind, UrlError};
+pub use server::{BoundServer, Server, ServerError};
 pub use service::gram_error::GramStdHttpErr;
+#[cfg(feature = "tls")]
+pub use tls::{TlsConfig, TlsError};
+#[cfg(unix)]
+pub use unix::PeerCredentials;
 
 #[cfg(feature = "route_builder")]
 pub use self::builder::route_parser;
+use crate::router::host::strip_port;
 use crate::router::Router as CoreRouter;
+use crate::service::IntoResponse;
 
 /**
 # Router
@@ -60,27 +88,68 @@ Build http 1.1 server.
 */
 pub async fn start(router: Router, addr: SocketAddr)
 {
-	let shared_app = Arc::new(router);
-
-	let new_service = make_service_fn(move |_| {
-		//this function will call for every connection
-		//init the app service
-		let app_capture = shared_app.clone();
-
-		async {
-			//return the result as async block
-			Ok::<_, Infallible>(service_fn(move |req| {
-				//this function will call for every request
-				handle_req(app_capture.clone(), req)
-			}))
-		}
-	});
+	listen(Server::new(router, addr)).await;
+}
+
+/**
+# Start listen and stop on the signal
+
+Like start, but when the signal future is ready, the server stops accepting new connections
+and waits until the open requests are done, but not longer than the drain timeout.
+Then the open connections are closed and the function returns.
+
+````ignore
+let signal = async {
+	tokio::signal::ctrl_c().await.unwrap();
+};
+
+rustgram::start_with_shutdown(router, addr, signal, Duration::from_secs(30)).await;
+````
+*/
+pub async fn start_with_shutdown<F>(router: Router, addr: SocketAddr, signal: F, drain_timeout: Duration)
+where
+	F: Future<Output = ()> + Send + 'static,
+{
+	listen(Server::new(router, addr).graceful_shutdown(signal, drain_timeout)).await;
+}
+
+/**
+# Start listen with a reloadable router
+
+Like start, but the router can be replaced with the reload function of the ReloadableRouter while the server runs.
+*/
+pub async fn start_reloadable(router: ReloadableRouter, addr: SocketAddr)
+{
+	listen(Server::reloadable(router, addr)).await;
+}
+
+/**
+Run the server for the start functions, panics on errors.
+*/
+async fn listen(server: Server)
+{
+	let server = server.bind().unwrap();
+
+	//start only listens on tcp
+	println!("Listening on http://{}", server.local_addr().unwrap());
+
+	server.serve().await.unwrap();
+}
+
+/**
+# Build the service of the router
 
-	let server = Server::bind(&addr).serve(new_service);
+The dispatch of the router wrapped into the layers of the router.
+Without an own 405 handler, the router answers with an empty 405 response.
+*/
+pub(crate) fn build_service(mut router: Router) -> RouteService<Request, Response>
+{
+	router.default_method_not_allowed();
 
-	println!("Listening on http://{}", addr);
+	let router = Arc::new(router);
+	let dispatch_router = router.clone();
 
-	server.await.unwrap();
+	router.layered(move |req| handle_req(dispatch_router.clone(), req))
 }
 
 /**
@@ -88,14 +157,946 @@ pub async fn start(router: Router, addr: SocketAddr)
 
 invoke the matched route.
 The matched route is a ref to a Box pointer
+
+When the 405 handler or the auto options handler was matched, the Allow header is set (if the handler didn't set it).
 */
-async fn handle_req(router: Arc<Router>, mut req: Request) -> Result<Response, Infallible>
+async fn handle_req(router: Arc<Router>, mut req: Request) -> Response
 {
-	let found = router.handle_req(req.method(), req.uri().path());
+	let (router, subdomain) = match request_host(&req).and_then(|host| router.route_host(host)) {
+		Some(r) => r,
+		None => (&*router, None),
+	};
+
+	let subdomain = subdomain.map(|s| Subdomain::new(s.to_string()));
+
+	let found = router.handle_req(req.method(), req.uri().path(), &req);
+
+	if let Some((status, path)) = found.redirect {
+		return redirect(status, path, req.uri().query());
+	}
+
+	if let Some(status) = found.reject {
+		return hyper::Response::builder()
+			.status(status)
+			.body(hyper::Body::empty())
+			.unwrap();
+	}
+
+	if let Some(e) = found.param_error {
+		return e.into_response();
+	}
 
 	req.extensions_mut().insert(found.params);
 
-	let res = found.handler.invoke(req).await;
+	if let Some(route) = found.route {
+		req.extensions_mut().insert(route);
+	}
+
+	if let Some(subdomain) = subdomain {
+		req.extensions_mut().insert(subdomain);
+	}
+
+	let mut res = found.handler.invoke(req).await;
+
+	if !found.allowed.is_empty() && !res.headers().contains_key(ALLOW) {
+		res.headers_mut()
+			.insert(ALLOW, allow_header(&found.allowed));
+	}
+
+	if found.strip_body {
+		res = strip_body(res);
+	}
+
+	res
+}
+
+/**
+Remove the body of the response from a get route for a head request.
+
+The Content-Length header is set from the body size, so the head response got the same headers as the get response.
+*/
+fn strip_body(res: Response) -> Response
+{
+	let (mut parts, body) = res.into_parts();
+
+	if !parts.headers.contains_key(CONTENT_LENGTH) {
+		if let Some(len) = body.size_hint().exact() {
+			parts.headers.insert(CONTENT_LENGTH, HeaderValue::from(len));
+		}
+	}
+
+	Response::from_parts(parts, hyper::Body::empty())
+}
+
+/**
+Redirect to the path of the route with the query of the request.
+*/
+fn redirect(status: StatusCode, path: String, query: Option<&str>) -> Response
+{
+	let location = match query {
+		Some(q) => path + "?" + q,
+		None => path,
+	};
+
+	hyper::Response::builder()
+		.status(status)
+		.header(LOCATION, location)
+		.body(hyper::Body::empty())
+		.unwrap()
+}
+
+/**
+The host of the request, from the uri or the Host header, without the port.
+*/
+fn request_host(req: &Request) -> Option<&str>
+{
+	if let Some(host) = req.uri().host() {
+		return Some(host);
+	}
+
+	let host = req.headers().get(HOST)?.to_str().ok()?;
+
+	Some(strip_port(host))
+}
+
+fn allow_header(methods: &[Method]) -> HeaderValue
+{
+	let allowed = methods
+		.iter()
+		.map(|m| m.as_str())
+		.collect::<Vec<_>>()
+		.join(", ");
+
+	//method names are valid header values
+	HeaderValue::from_str(&allowed).unwrap()
+}
+
+#[cfg(test)]
+mod test
+{
+	use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+	use super::*;
+	use crate::service::Service;
+
+	/**
+	Send a http 1.1 request without keep alive and return the response
+	*/
+	async fn send_req(addr: SocketAddr, path: &str) -> String
+	{
+		let mut stream = tokio::net::TcpStream::connect(addr).await.unwrap();
+
+		stream
+			.write_all(
+				format!(
+					"GET {} HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n",
+					path
+				)
+				.as_bytes(),
+			)
+			.await
+			.unwrap();
+
+		let mut res = String::new();
+
+		stream.read_to_string(&mut res).await.unwrap();
+
+		res
+	}
+
+	#[tokio::test]
+	async fn test_405_allow_header()
+	{
+		let mut router = Router::new(|_req: Request| async { "404".to_string() });
+
+		router.method_not_allowed(|_req: Request| {
+			async {
+				hyper::Response::builder()
+					.status(StatusCode::METHOD_NOT_ALLOWED)
+					.body(hyper::Body::empty())
+					.unwrap()
+			}
+		});
+
+		router.get("/test", r(|_req: Request| async { "test".to_string() }));
+		router.post("/test", r(|_req: Request| async { "test".to_string() }));
+
+		let req = hyper::Request::builder()
+			.method(Method::DELETE)
+			.uri("/test")
+			.body(hyper::Body::empty())
+			.unwrap();
+
+		let res = handle_req(Arc::new(router), req).await;
+
+		assert_eq!(res.status(), StatusCode::METHOD_NOT_ALLOWED);
+		assert_eq!(res.headers().get(ALLOW).unwrap(), "GET, POST");
+	}
+
+	#[tokio::test]
+	async fn test_default_405()
+	{
+		let mut router = Router::new(|_req: Request| async { "404".to_string() });
+
+		router.get("/test", r(|_req: Request| async { "test".to_string() }));
+
+		let service = build_service(router);
+
+		let req = hyper::Request::builder()
+			.method(Method::DELETE)
+			.uri("/test")
+			.body(hyper::Body::empty())
+			.unwrap();
+
+		let res = service.call(req).await;
+
+		assert_eq!(res.status(), StatusCode::METHOD_NOT_ALLOWED);
+		assert_eq!(res.headers().get(ALLOW).unwrap(), "GET");
+
+		//unknown path
+		let req = hyper::Request::builder()
+			.method(Method::DELETE)
+			.uri("/other")
+			.body(hyper::Body::empty())
+			.unwrap();
+
+		let body = hyper::body::to_bytes(service.call(req).await.into_body())
+			.await
+			.unwrap();
+
+		assert_eq!(body, "404");
+	}
+
+	#[tokio::test]
+	async fn test_auto_head()
+	{
+		let mut router = Router::new(|_req: Request| async { "404".to_string() });
+
+		router.auto_head(true);
+
+		router.get(
+			"/test",
+			r(|_req: Request| {
+				async {
+					hyper::Response::builder()
+						.header("x-test", "test")
+						.body(hyper::Body::from("test"))
+						.unwrap()
+				}
+			}),
+		);
+
+		let req = hyper::Request::builder()
+			.method(Method::HEAD)
+			.uri("/test")
+			.body(hyper::Body::empty())
+			.unwrap();
+
+		let res = handle_req(Arc::new(router), req).await;
+
+		assert_eq!(res.status(), StatusCode::OK);
+		assert_eq!(res.headers().get("x-test").unwrap(), "test");
+		assert_eq!(res.headers().get(CONTENT_LENGTH).unwrap(), "4");
+
+		let body = hyper::body::to_bytes(res.into_body()).await.unwrap();
+
+		assert!(body.is_empty());
+	}
+
+	#[tokio::test]
+	async fn test_matched_route()
+	{
+		let mut router = Router::new(|req: Request| {
+			async move {
+				//no route matched
+				assert!(req.extensions().get::<MatchedRoute>().is_none());
+
+				"404".to_string()
+			}
+		});
+
+		router.group("/admin", |g| {
+			g.get(
+				"/users/:id",
+				r(|req: Request| {
+					async move {
+						let route = req.extensions().get::<MatchedRoute>().unwrap();
+
+						format!("{} {} {}", route.method(), route.pattern(), route.name().unwrap())
+					}
+				}),
+			)
+			.name("user.show");
+		});
+
+		let router = Arc::new(router);
+
+		let req = hyper::Request::builder()
+			.uri("/admin/users/123")
+			.body(hyper::Body::empty())
+			.unwrap();
+
+		let res = handle_req(router.clone(), req).await;
+
+		let body = hyper::body::to_bytes(res.into_body()).await.unwrap();
+
+		assert_eq!(body, "GET /admin/users/:id user.show");
+
+		let req = hyper::Request::builder()
+			.uri("/users/123")
+			.body(hyper::Body::empty())
+			.unwrap();
+
+		let res = handle_req(router, req).await;
+
+		let body = hyper::body::to_bytes(res.into_body()).await.unwrap();
+
+		assert_eq!(body, "404");
+	}
+
+	#[tokio::test]
+	async fn test_invalid_param()
+	{
+		let mut router = Router::new(|_req: Request| async { "404".to_string() });
+
+		router.get("/files/:file", r(|_req: Request| async { "test".to_string() }));
+
+		let req = hyper::Request::builder()
+			.uri("/files/%FF%FE")
+			.body(hyper::Body::empty())
+			.unwrap();
+
+		let res = handle_req(Arc::new(router), req).await;
+
+		assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+	}
+
+	#[tokio::test]
+	async fn test_trailing_slash_redirect()
+	{
+		let mut router = Router::new(|_req: Request| async { "404".to_string() });
+
+		router.trailing_slash(TrailingSlash::Redirect308);
+
+		router.get("/users", r(|_req: Request| async { "test".to_string() }));
+		router.get("/:a/:b", r(|_req: Request| async { "test".to_string() }));
+
+		let router = Arc::new(router);
+
+		let req = hyper::Request::builder()
+			.uri("/users/?page=2")
+			.body(hyper::Body::empty())
+			.unwrap();
+
+		let res = handle_req(router.clone(), req).await;
+
+		assert_eq!(res.status(), StatusCode::PERMANENT_REDIRECT);
+		assert_eq!(res.headers().get(LOCATION).unwrap(), "/users?page=2");
+
+		//no redirect to another host
+		let req = hyper::Request::builder()
+			.uri("//evil.com/")
+			.body(hyper::Body::empty())
+			.unwrap();
+
+		let res = handle_req(router, req).await;
+
+		assert!(res.headers().get(LOCATION).is_none());
+
+		let body = hyper::body::to_bytes(res.into_body()).await.unwrap();
+
+		assert_eq!(body, "404");
+	}
+
+	#[tokio::test]
+	async fn test_host()
+	{
+		let mut router = Router::new(|_req: Request| async { "404".to_string() });
+
+		router.get("/", r(|_req: Request| async { "main".to_string() }));
+
+		let mut tenant = Router::new(|_req: Request| async { "404".to_string() });
+
+		tenant.get(
+			"/users/:id",
+			r(|req: Request| {
+				async move {
+					let params = req.extensions().get::<RouteParams>().unwrap();
+					let subdomain = req.extensions().get::<Subdomain>().unwrap();
+
+					format!("{} {}", subdomain.as_str(), params.get("id").unwrap())
+				}
+			}),
+		);
+
+		router.host("*.tenant.example.com", tenant);
+
+		let router = Arc::new(router);
+
+		let req = hyper::Request::builder()
+			.uri("/users/42")
+			.header(HOST, "acme.tenant.example.com:8080")
+			.body(hyper::Body::empty())
+			.unwrap();
+
+		let res = handle_req(router.clone(), req).await;
+
+		let body = hyper::body::to_bytes(res.into_body()).await.unwrap();
+
+		assert_eq!(body, "acme 42");
+
+		//uri authority
+		let req = hyper::Request::builder()
+			.uri("http://acme.tenant.example.com/users/1")
+			.body(hyper::Body::empty())
+			.unwrap();
+
+		let res = handle_req(router.clone(), req).await;
+
+		let body = hyper::body::to_bytes(res.into_body()).await.unwrap();
+
+		assert_eq!(body, "acme 1");
+
+		let req = hyper::Request::builder()
+			.uri("/")
+			.header(HOST, "example.com")
+			.body(hyper::Body::empty())
+			.unwrap();
+
+		let res = handle_req(router, req).await;
+
+		let body = hyper::body::to_bytes(res.into_body()).await.unwrap();
+
+		assert_eq!(body, "main");
+	}
+
+	#[tokio::test]
+	async fn test_guard_unsupported_media_type()
+	{
+		let mut router = Router::new(|_req: Request| async { "404".to_string() });
+
+		router.post(
+			"/upload",
+			r(|_req: Request| async { "upload".to_string() }).guard(guard::content_type("multipart/form-data")),
+		);
+
+		let req = hyper::Request::builder()
+			.method(Method::POST)
+			.uri("/upload")
+			.header("Content-Type", "application/json")
+			.body(hyper::Body::empty())
+			.unwrap();
+
+		let res = handle_req(Arc::new(router), req).await;
+
+		assert_eq!(res.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+	}
+
+	#[tokio::test]
+	async fn test_layer()
+	{
+		let mut router = Router::new(|_req: Request| async { "404".to_string() });
+
+		router.method_not_allowed(|_req: Request| async { "405".to_string() });
+
+		router.get("/test", r(|_req: Request| async { "test".to_string() }));
+
+		let header_layer = |name: &'static str| {
+			move |inner: RouteService<Request, Response>| {
+				let inner = Arc::new(inner);
+
+				move |req: Request| {
+					let inner = inner.clone();
+
+					async move {
+						let mut res = inner.call(req).await;
+
+						res.headers_mut()
+							.append("x-layer", HeaderValue::from_static(name));
+
+						res
+					}
+				}
+			}
+		};
+
+		router.layer(header_layer("inner"));
+		router.layer(header_layer("outer"));
+
+		let service = build_service(router);
+
+		for (method, uri) in [(Method::GET, "/test"), (Method::GET, "/other"), (Method::POST, "/test")] {
+			let req = hyper::Request::builder()
+				.method(method)
+				.uri(uri)
+				.body(hyper::Body::empty())
+				.unwrap();
+
+			let res = service.call(req).await;
+
+			let layers = res.headers().get_all("x-layer").iter().collect::<Vec<_>>();
+
+			assert_eq!(layers, ["inner", "outer"]);
+		}
+	}
+
+	#[tokio::test]
+	async fn test_reloadable_router()
+	{
+		let mut router = Router::new(|_req: Request| async { "404".to_string() });
+
+		router.get("/test", r(|_req: Request| async { "old".to_string() }));
+
+		let reloadable = ReloadableRouter::new(router);
+
+		//a request which started before the reload
+		let old_service = reloadable.current();
+
+		let mut router = Router::new(|_req: Request| async { "404".to_string() });
+
+		router.get("/test", r(|_req: Request| async { "new".to_string() }));
+
+		reloadable.clone().reload(router);
+
+		let res = old_service
+			.call(
+				hyper::Request::builder()
+					.uri("/test")
+					.body(hyper::Body::empty())
+					.unwrap(),
+			)
+			.await;
+
+		let body = hyper::body::to_bytes(res.into_body()).await.unwrap();
+
+		assert_eq!(body, "old");
+
+		let res = reloadable
+			.current()
+			.call(
+				hyper::Request::builder()
+					.uri("/test")
+					.body(hyper::Body::empty())
+					.unwrap(),
+			)
+			.await;
+
+		let body = hyper::body::to_bytes(res.into_body()).await.unwrap();
+
+		assert_eq!(body, "new");
+	}
+
+	#[tokio::test]
+	async fn test_start_with_shutdown()
+	{
+		let router = Router::new(|_req: Request| async { "404".to_string() });
+
+		let (tx, rx) = tokio::sync::oneshot::channel::<()>();
+
+		let server = tokio::spawn(start_with_shutdown(
+			router,
+			([127, 0, 0, 1], 0).into(),
+			async {
+				let _ = rx.await;
+			},
+			Duration::from_secs(1),
+		));
+
+		tx.send(()).unwrap();
+
+		tokio::time::timeout(Duration::from_secs(5), server)
+			.await
+			.unwrap()
+			.unwrap();
+	}
+
+	/**
+	Start a server with a slow handler and stop it while the request is running.
+
+	Returns the response of the request and if the server stopped in time.
+	*/
+	async fn shutdown_slow_req(handler_time: Duration, drain_timeout: Duration) -> (String, bool)
+	{
+		let (started_tx, mut started_rx) = tokio::sync::mpsc::unbounded_channel::<()>();
+
+		let mut router = Router::new(|_req: Request| async { "404".to_string() });
+
+		router.get(
+			"/slow",
+			r(move |_req: Request| {
+				let started_tx = started_tx.clone();
+
+				async move {
+					let _ = started_tx.send(());
+
+					tokio::time::sleep(handler_time).await;
+
+					"slow".to_string()
+				}
+			}),
+		);
+
+		let (tx, rx) = tokio::sync::oneshot::channel::<()>();
+
+		let server = Server::new(router, ([127, 0, 0, 1], 0).into())
+			.graceful_shutdown(
+				async {
+					let _ = rx.await;
+				},
+				drain_timeout,
+			)
+			.bind()
+			.unwrap();
+
+		let addr = server.local_addr().unwrap();
+
+		let server = tokio::spawn(server.serve());
+
+		let req = tokio::spawn(async move {
+			let mut stream = tokio::net::TcpStream::connect(addr).await.unwrap();
+
+			stream
+				.write_all(b"GET /slow HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n")
+				.await
+				.unwrap();
+
+			let mut res = String::new();
+
+			//the connection is closed without a response after the drain timeout
+			let _ = stream.read_to_string(&mut res).await;
+
+			res
+		});
+
+		//shutdown while the request is running
+		started_rx.recv().await.unwrap();
+
+		tx.send(()).unwrap();
+
+		let stopped = tokio::time::timeout(Duration::from_secs(5), server)
+			.await
+			.map(|res| res.unwrap().is_ok())
+			.unwrap_or(false);
+
+		(req.await.unwrap(), stopped)
+	}
+
+	#[tokio::test]
+	async fn test_shutdown_finishes_open_requests()
+	{
+		let (res, stopped) = shutdown_slow_req(Duration::from_millis(300), Duration::from_secs(5)).await;
+
+		assert!(stopped);
+		assert!(res.starts_with("HTTP/1.1 200 OK"));
+		assert!(res.ends_with("slow"));
+	}
+
+	#[tokio::test]
+	async fn test_shutdown_drain_timeout()
+	{
+		let start = tokio::time::Instant::now();
+
+		let (res, stopped) = shutdown_slow_req(Duration::from_secs(30), Duration::from_millis(200)).await;
+
+		//the server stopped after the drain timeout, not after the request
+		assert!(stopped);
+		assert!(start.elapsed() < Duration::from_secs(5));
+		assert!(!res.contains("slow"));
+	}
+
+	#[tokio::test]
+	async fn test_server()
+	{
+		let mut router = Router::new(|_req: Request| async { "404".to_string() });
+
+		router.get("/test", r(|_req: Request| async { "test".to_string() }));
+
+		let (tx, rx) = tokio::sync::oneshot::channel::<()>();
+
+		let server = Server::new(router, ([127, 0, 0, 1], 0).into())
+			.http1_keep_alive(false)
+			.http1_header_read_timeout(Duration::from_secs(5))
+			.http1_max_buf_size(16 * 1024)
+			.tcp_nodelay(true)
+			.graceful_shutdown(
+				async {
+					let _ = rx.await;
+				},
+				Duration::from_secs(1),
+			)
+			.bind()
+			.unwrap();
+
+		let addr = server.local_addr().unwrap();
+
+		assert_ne!(addr.port(), 0);
+
+		let handle = tokio::spawn(server.serve());
+
+		let res = send_req(addr, "/test").await;
+
+		assert!(res.starts_with("HTTP/1.1 200 OK"));
+		assert!(res.ends_with("test"));
+
+		//hyper panics with a smaller buffer
+		let router = Router::new(|_req: Request| async { "404".to_string() });
+
+		let err = Server::new(router, ([127, 0, 0, 1], 0).into())
+			.http1_max_buf_size(1024)
+			.bind()
+			.err()
+			.unwrap();
+
+		assert!(matches!(err, ServerError::Config(_)));
+
+		//the port is in use
+		let router = Router::new(|_req: Request| async { "404".to_string() });
+
+		let err = Server::new(router, addr).run().await.unwrap_err();
+
+		assert!(matches!(err, ServerError::Bind(_)));
+
+		tx.send(()).unwrap();
+
+		handle.await.unwrap().unwrap();
+	}
+
+	#[cfg(feature = "tls")]
+	#[tokio::test]
+	async fn test_tls_server()
+	{
+		use tokio_rustls::rustls;
+
+		let cert = include_bytes!("../tests/tls/cert.pem");
+		let key = include_bytes!("../tests/tls/key.pem");
+		let ca = include_bytes!("../tests/tls/ca.pem");
+
+		assert!(matches!(TlsConfig::from_pem(b"", key), Err(TlsError::NoCertificate)));
+		assert!(matches!(TlsConfig::from_pem(cert, b""), Err(TlsError::NoPrivateKey)));
+
+		let tls = TlsConfig::from_pem(cert, key).unwrap();
+
+		//reload the same certificate from the files
+		tls.reload_from_pem_files("tests/tls/cert.pem", "tests/tls/key.pem")
+			.unwrap();
+
+		let mut router = Router::new(|_req: Request| async { "404".to_string() });
+
+		router.get("/test", r(|_req: Request| async { "test".to_string() }));
+
+		let err = Server::new(
+			Router::new(|_req: Request| async { "404".to_string() }),
+			([127, 0, 0, 1], 0).into(),
+		)
+		.tls(tls.clone())
+		.tls_max_handshakes(0)
+		.bind()
+		.err()
+		.unwrap();
+
+		assert!(matches!(err, ServerError::Config(_)));
+
+		let server = Server::new(router, ([127, 0, 0, 1], 0).into())
+			.tls(tls)
+			.tls_handshake_timeout(Duration::from_millis(200))
+			.tls_max_handshakes(1)
+			.bind()
+			.unwrap();
+
+		let addr = server.local_addr().unwrap();
+
+		tokio::spawn(server.serve());
+
+		//a client which never starts the handshake holds the only handshake until the timeout
+		let mut idle = tokio::net::TcpStream::connect(addr).await.unwrap();
+		let start = std::time::Instant::now();
+
+		let mut roots = rustls::RootCertStore::empty();
+
+		for ca in rustls_pemfile::certs(&mut &ca[..]).unwrap() {
+			roots.add(&rustls::Certificate(ca)).unwrap();
+		}
+
+		let mut config = rustls::ClientConfig::builder()
+			.with_safe_defaults()
+			.with_root_certificates(roots)
+			.with_no_client_auth();
+
+		config.alpn_protocols = vec![b"http/1.1".to_vec()];
+
+		let connector = tokio_rustls::TlsConnector::from(Arc::new(config));
+
+		let stream = tokio::net::TcpStream::connect(addr).await.unwrap();
+
+		let mut stream = connector
+			.connect(rustls::ServerName::try_from("localhost").unwrap(), stream)
+			.await
+			.unwrap();
+
+		assert_eq!(stream.get_ref().1.alpn_protocol(), Some(&b"http/1.1"[..]));
+		assert!(start.elapsed() >= Duration::from_millis(150));
+
+		//the idle connection was closed
+		let mut buf = Vec::new();
+
+		assert_eq!(idle.read_to_end(&mut buf).await.unwrap(), 0);
+
+		stream
+			.write_all(b"GET /test HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n")
+			.await
+			.unwrap();
+
+		let mut res = String::new();
+
+		stream.read_to_string(&mut res).await.unwrap();
+
+		assert!(res.starts_with("HTTP/1.1 200 OK"));
+		assert!(res.ends_with("test"));
+	}
+
+	#[cfg(unix)]
+	#[tokio::test]
+	async fn test_unix_server()
+	{
+		use std::os::unix::fs::{MetadataExt, PermissionsExt};
+
+		let path = std::env::temp_dir().join(format!("rustgram_test_{}.sock", std::process::id()));
+
+		//a stale socket file of a closed listener is removed before bind
+		drop(std::os::unix::net::UnixListener::bind(&path).unwrap());
+
+		assert!(path.exists());
+
+		let mut router = Router::new(|_req: Request| async { "404".to_string() });
+
+		router.get(
+			"/test",
+			r(|req: Request| {
+				async move {
+					let peer = req.extensions().get::<PeerCredentials>().unwrap();
+
+					format!("{} {}", peer.uid, peer.gid)
+				}
+			}),
+		);
+
+		let (tx, rx) = tokio::sync::oneshot::channel::<()>();
+
+		let server = Server::unix(router, &path)
+			.unix_permissions(0o660)
+			.graceful_shutdown(
+				async {
+					let _ = rx.await;
+				},
+				Duration::from_secs(1),
+			)
+			.bind()
+			.unwrap();
+
+		assert!(server.local_addr().is_none());
+
+		let meta = std::fs::metadata(&path).unwrap();
+
+		assert_eq!(meta.permissions().mode() & 0o777, 0o660);
+
+		//the socket is in use
+		let router = Router::new(|_req: Request| async { "404".to_string() });
+
+		let err = Server::unix(router, &path)
+			.unix_permissions(0o660)
+			.bind()
+			.err()
+			.unwrap();
+
+		assert!(matches!(err, ServerError::Socket(_)));
+
+		let handle = tokio::spawn(server.serve());
+
+		let mut stream = tokio::net::UnixStream::connect(&path).await.unwrap();
+
+		stream
+			.write_all(b"GET /test HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n")
+			.await
+			.unwrap();
+
+		let mut res = String::new();
+
+		stream.read_to_string(&mut res).await.unwrap();
+
+		//the socket file is owned by the user of this process
+		assert!(res.ends_with(&format!("{} {}", meta.uid(), meta.gid())));
+
+		tx.send(()).unwrap();
+
+		handle.await.unwrap().unwrap();
+
+		assert!(!path.exists());
+	}
+
+	#[tokio::test]
+	async fn test_server_from_listener()
+	{
+		let mut router = Router::new(|_req: Request| async { "404".to_string() });
+
+		router.get("/test", r(|_req: Request| async { "test".to_string() }));
+
+		let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+		let addr = listener.local_addr().unwrap();
+
+		let server = Server::from_std_listener(router, listener).bind().unwrap();
+
+		assert_eq!(server.local_addr(), Some(addr));
+
+		tokio::spawn(server.serve());
+
+		let res = send_req(addr, "/test").await;
+
+		assert!(res.ends_with("test"));
+
+		let router = Router::new(|_req: Request| async { "404".to_string() });
+
+		let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+		let addr = listener.local_addr().unwrap();
+
+		tokio::spawn(Server::from_listener(router, listener).run());
+
+		let res = send_req(addr, "/test").await;
+
+		assert!(res.ends_with("404"));
+	}
+
+	#[cfg(unix)]
+	#[test]
+	fn test_listen_fds()
+	{
+		use std::os::unix::io::AsRawFd;
+
+		use crate::listen_fd::{is_tcp_listener, passed_fds};
+
+		let pid = std::process::id().to_string();
+		let other_pid = (std::process::id() + 1).to_string();
+
+		assert_eq!(passed_fds(Some(&pid), Some("2")).unwrap(), 2);
+		assert_eq!(passed_fds(Some(&pid), None).unwrap(), 0);
+
+		//the sockets are for another process
+		assert_eq!(passed_fds(Some(&other_pid), Some("2")).unwrap(), 0);
+		assert_eq!(passed_fds(None, Some("2")).unwrap(), 0);
+
+		assert!(passed_fds(Some("abc"), Some("2")).is_err());
+		assert!(passed_fds(Some(&pid), Some("abc")).is_err());
+
+		//only listening tcp sockets are taken
+		let tcp = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+		let udp = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+
+		let path = std::env::temp_dir().join(format!("rustgram_test_fds_{}.sock", std::process::id()));
+		let unix = std::os::unix::net::UnixListener::bind(&path).unwrap();
+
+		assert!(is_tcp_listener(tcp.as_raw_fd()));
+		assert!(!is_tcp_listener(udp.as_raw_fd()));
+		assert!(!is_tcp_listener(unix.as_raw_fd()));
 
-	Ok(res)
+		std::fs::remove_file(&path).unwrap();
+	}
 }
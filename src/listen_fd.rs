@@ -0,0 +1,164 @@
+use std::net::TcpListener;
+use std::os::unix::io::{FromRawFd, RawFd};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::{io, mem};
+
+/// the first passed socket, after stdin, stdout and stderr
+const LISTEN_FDS_START: RawFd = 3;
+
+/// the passed fds are only taken once per process
+static TAKEN: AtomicBool = AtomicBool::new(false);
+
+/**
+# Get the tcp listeners which were passed by systemd socket activation
+
+Reads the `LISTEN_FDS` and `LISTEN_PID` env vars, which are set by systemd or by launchers like systemfd
+(e.g. with cargo watch for restarts without closing the socket).
+
+Only on unix. Returns an empty vec when no sockets were passed to this process.
+Passed sockets which are not listening tcp sockets (e.g. unix or udp sockets) are skipped and kept open.
+The taken sockets are closed on exec, so they are not passed to child processes.
+
+The sockets are only taken once, later calls return an empty vec.
+The env vars are not removed, because changing the env is not thread safe (e.g. in the tokio runtime).
+Child processes don't take the sockets, because `LISTEN_PID` is not their pid.
+To remove the env vars anyway, call `std::env::remove_var` before any threads are spawned.
+
+````ignore
+let server = match rustgram::listen_fds()?.pop() {
+	Some(listener) => rustgram::Server::from_std_listener(router, listener),
+	None => rustgram::Server::new(router, addr),
+};
+
+server.run().await?;
+````
+*/
+pub fn listen_fds() -> io::Result<Vec<TcpListener>>
+{
+	if TAKEN.swap(true, Ordering::SeqCst) {
+		return Ok(Vec::new());
+	}
+
+	let pid = std::env::var("LISTEN_PID").ok();
+	let fds = std::env::var("LISTEN_FDS").ok();
+
+	let fds = passed_fds(pid.as_deref(), fds.as_deref())?;
+
+	let mut listeners = Vec::with_capacity(fds);
+
+	for fd in LISTEN_FDS_START..LISTEN_FDS_START + fds as RawFd {
+		if !is_tcp_listener(fd) {
+			//e.g. a udp or unix socket, don't close it
+			continue;
+		}
+
+		set_cloexec(fd)?;
+
+		//safety: the fds were passed to this process (checked with LISTEN_PID) and are only taken once,
+		//so every fd is only owned by one listener
+		listeners.push(unsafe { TcpListener::from_raw_fd(fd) });
+	}
+
+	Ok(listeners)
+}
+
+/**
+The number of passed fds for this process, from the values of `LISTEN_PID` and `LISTEN_FDS`.
+
+Like sd_listen_fds, the fds are only for this process when `LISTEN_PID` is set to the pid of this process.
+*/
+pub(crate) fn passed_fds(listen_pid: Option<&str>, listen_fds: Option<&str>) -> io::Result<usize>
+{
+	let pid = match listen_pid {
+		Some(pid) => {
+			pid.parse::<u32>()
+				.map_err(|e| io::Error::new(io::ErrorKind::InvalidData, format!("Invalid LISTEN_PID: {}", e)))?
+		},
+		None => return Ok(0),
+	};
+
+	if pid != std::process::id() {
+		//the sockets are for another process
+		return Ok(0);
+	}
+
+	match listen_fds {
+		Some(fds) => {
+			fds.parse::<usize>()
+				.map_err(|e| io::Error::new(io::ErrorKind::InvalidData, format!("Invalid LISTEN_FDS: {}", e)))
+		},
+		None => Ok(0),
+	}
+}
+
+/**
+Check if the fd is an ipv4 or ipv6 stream socket which is listening
+*/
+pub(crate) fn is_tcp_listener(fd: RawFd) -> bool
+{
+	matches!(socket_family(fd), Some(libc::AF_INET | libc::AF_INET6)) &&
+		socket_option(fd, libc::SO_TYPE) == Some(libc::SOCK_STREAM) &&
+		socket_option(fd, libc::SO_ACCEPTCONN) == Some(1)
+}
+
+/**
+The address family of the socket, from the local address.
+
+Used instead of SO_DOMAIN, which is not available on every unix.
+*/
+fn socket_family(fd: RawFd) -> Option<libc::c_int>
+{
+	//safety: sockaddr_storage is a plain c struct, zeroed is valid
+	let mut addr: libc::sockaddr_storage = unsafe { mem::zeroed() };
+	let mut len = mem::size_of::<libc::sockaddr_storage>() as libc::socklen_t;
+
+	//safety: the addr and the len are valid for every address family
+	let res = unsafe {
+		libc::getsockname(
+			fd,
+			&mut addr as *mut libc::sockaddr_storage as *mut libc::sockaddr,
+			&mut len,
+		)
+	};
+
+	if res == 0 {
+		Some(addr.ss_family as libc::c_int)
+	} else {
+		None
+	}
+}
+
+fn socket_option(fd: RawFd, option: libc::c_int) -> Option<libc::c_int>
+{
+	let mut value: libc::c_int = 0;
+	let mut len = mem::size_of::<libc::c_int>() as libc::socklen_t;
+
+	//safety: the value and the len are valid for the int option
+	let res = unsafe {
+		libc::getsockopt(
+			fd,
+			libc::SOL_SOCKET,
+			option,
+			&mut value as *mut libc::c_int as *mut libc::c_void,
+			&mut len,
+		)
+	};
+
+	if res == 0 {
+		Some(value)
+	} else {
+		None
+	}
+}
+
+fn set_cloexec(fd: RawFd) -> io::Result<()>
+{
+	//safety: only the fd flags are read and set
+	let flags = unsafe { libc::fcntl(fd, libc::F_GETFD) };
+
+	if flags < 0 || unsafe { libc::fcntl(fd, libc::F_SETFD, flags | libc::FD_CLOEXEC) } < 0 {
+		return Err(io::Error::last_os_error());
+	}
+
+	Ok(())
+}
@@ -0,0 +1,58 @@
+use std::sync::{Arc, RwLock};
+
+use crate::router::route::RouteService;
+use crate::{build_service, Request, Response, Router};
+
+/**
+# A router which can be replaced while the server runs
+
+Cloning the handle is cheap, all clones share the same router.
+
+Requests which already started are finished by the old router,
+new requests are handled by the new router.
+
+````ignore
+let router = ReloadableRouter::new(routes());
+
+tokio::spawn(rustgram::start_reloadable(router.clone(), addr));
+
+//later, e.g. after the route file changed
+router.reload(new_routes());
+````
+*/
+#[derive(Clone)]
+pub struct ReloadableRouter
+{
+	service: Arc<RwLock<Arc<RouteService<Request, Response>>>>,
+}
+
+impl ReloadableRouter
+{
+	pub fn new(router: Router) -> Self
+	{
+		Self {
+			service: Arc::new(RwLock::new(Arc::new(build_service(router)))),
+		}
+	}
+
+	/**
+	# Swap in a new router
+
+	The new router is build (incl. the layers) before the old one is replaced.
+	*/
+	pub fn reload(&self, router: Router)
+	{
+		let service = Arc::new(build_service(router));
+
+		//the lock is never held while a request is handled, so it can't be poisoned by a handler
+		*self.service.write().unwrap() = service;
+	}
+
+	/**
+	The current router, the request is handled by this router even when the router is replaced meanwhile
+	*/
+	pub(crate) fn current(&self) -> Arc<RouteService<Request, Response>>
+	{
+		self.service.read().unwrap().clone()
+	}
+}
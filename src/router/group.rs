@@ -0,0 +1,80 @@
+use std::ops::Range;
+use std::sync::Arc;
+
+use hyper::Method;
+
+use crate::router::route::{GramRoute, RouteService};
+use crate::router::{DynRoute, RouteId, Router};
+use crate::service::{Service, ServiceTransform};
+
+type SharedRoute<Req, Res> = Arc<DynRoute<Req, Res>>;
+
+/**
+# A group of routes
+
+Returned by the router group function.
+
+Holds the routes which were added inside the group closure,
+so middleware can be applied to all routes of the group.
+*/
+pub struct RouteGroup<'a, Req, Res>
+where
+	Req: Send + Sync + 'static,
+	Res: Send + Sync + 'static,
+{
+	router: &'a mut Router<Req, Res>,
+	routes: Vec<(Method, Range<RouteId>)>,
+}
+
+impl<'a, Req, Res> RouteGroup<'a, Req, Res>
+where
+	Req: Send + Sync + 'static,
+	Res: Send + Sync + 'static,
+{
+	pub(crate) fn new(router: &'a mut Router<Req, Res>, routes: Vec<(Method, Range<RouteId>)>) -> Self
+	{
+		Self {
+			router,
+			routes,
+		}
+	}
+
+	/**
+	# Apply a middleware to every route of the group
+
+	The middleware is applied after the route middleware and the middleware of nested groups.
+	*/
+	#[allow(clippy::should_implement_trait)]
+	pub fn add<T, S1>(self, middleware: T) -> Self
+	where
+		T: ServiceTransform<RouteService<Req, Res>, Service = S1>,
+		S1: Service<Req, Output = Res>,
+	{
+		//routes which are shared between methods (any and on) are only wrapped once
+		let mut wrapped: Vec<(SharedRoute<Req, Res>, SharedRoute<Req, Res>)> = Vec::new();
+
+		for (method, ids) in &self.routes {
+			if let Some(router) = self.router.method_router_mut(method) {
+				for entry in &mut router.routes[ids.clone()] {
+					if let Some((_, route)) = wrapped
+						.iter()
+						.find(|(old, _)| Arc::ptr_eq(old, &entry.route))
+					{
+						entry.route = route.clone();
+						continue;
+					}
+
+					let inner = RouteService::new(entry.route.clone());
+
+					let route: SharedRoute<Req, Res> = Arc::new(GramRoute::new(middleware.transform(inner)));
+
+					wrapped.push((entry.route.clone(), route.clone()));
+
+					entry.route = route;
+				}
+			}
+		}
+
+		self
+	}
+}
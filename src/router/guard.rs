@@ -0,0 +1,176 @@
+use hyper::header::{HeaderName, CONTENT_TYPE};
+use hyper::StatusCode;
+use percent_encoding::percent_decode_str;
+
+/**
+# An extra predicate of a route
+
+The route is only matched when all of its guards accept the request.
+Routes with guards can share the method and the path with other routes.
+
+Closures which take a reference to the request are guards too:
+
+````ignore
+router.get("/users", r(users_handler).guard(|req: &Request| req.headers().contains_key("x-admin")));
+````
+*/
+pub trait Guard<Req>: Send + Sync
+{
+	fn check(&self, req: &Req) -> bool;
+
+	/**
+	The status of the response when no route of the path accepted the request because of this guard.
+
+	Not found by default. The 404 handler is called for this status.
+	*/
+	fn reject_status(&self) -> StatusCode
+	{
+		StatusCode::NOT_FOUND
+	}
+}
+
+impl<F, Req> Guard<Req> for F
+where
+	F: Fn(&Req) -> bool + Send + Sync,
+{
+	fn check(&self, req: &Req) -> bool
+	{
+		self(req)
+	}
+}
+
+//__________________________________________________________________________________________________
+
+/**
+Check if one of the comma separated values of the header is the value.
+
+The parameters of a value (after the `;`) are ignored and the values are compared case insensitive.
+*/
+fn header_contains(header: &str, value: &str) -> bool
+{
+	header.split(',').any(|v| {
+		let v = v.split(';').next().unwrap_or_default();
+
+		v.trim().eq_ignore_ascii_case(value)
+	})
+}
+
+/**
+# Guard for a header value
+
+Accepts the request when one of the comma separated values of the header is the value (case insensitive),
+e.g. for api versioning by the Accept header:
+
+````ignore
+use rustgram::guard::header;
+
+router.get("/users", r(users_v2).guard(header("Accept", "application/vnd.v2+json")));
+router.get("/users", r(users_v1));
+````
+*/
+pub struct Header
+{
+	name: HeaderName,
+	value: String,
+}
+
+pub fn header(name: &str, value: &str) -> Header
+{
+	Header {
+		name: HeaderName::from_bytes(name.as_bytes()).expect("Not a valid header name"),
+		value: value.to_string(),
+	}
+}
+
+impl<B> Guard<hyper::Request<B>> for Header
+{
+	fn check(&self, req: &hyper::Request<B>) -> bool
+	{
+		req.headers()
+			.get_all(&self.name)
+			.iter()
+			.filter_map(|v| v.to_str().ok())
+			.any(|v| header_contains(v, &self.value))
+	}
+}
+
+/**
+# Guard for the content type of the request body
+
+The mime type is compared without the parameters (e.g. the boundary of multipart/form-data).
+
+When no route of the path accepted the content type, the request is answered with `415 Unsupported Media Type`.
+*/
+pub struct ContentType(String);
+
+pub fn content_type(mime: &str) -> ContentType
+{
+	ContentType(mime.to_string())
+}
+
+impl<B> Guard<hyper::Request<B>> for ContentType
+{
+	fn check(&self, req: &hyper::Request<B>) -> bool
+	{
+		match req.headers().get(CONTENT_TYPE).map(|v| v.to_str()) {
+			Some(Ok(v)) => {
+				let mime = v.split(';').next().unwrap_or_default();
+
+				mime.trim().eq_ignore_ascii_case(&self.0)
+			},
+			_ => false,
+		}
+	}
+
+	fn reject_status(&self) -> StatusCode
+	{
+		StatusCode::UNSUPPORTED_MEDIA_TYPE
+	}
+}
+
+/**
+# Guard for a query param
+
+Accepts the request when the query string got the param with the value. The query is percent decoded.
+
+````ignore
+router.get("/export", r(export_csv).guard(query("format", "csv")));
+````
+*/
+pub struct Query
+{
+	key: String,
+	value: String,
+}
+
+pub fn query(key: &str, value: &str) -> Query
+{
+	Query {
+		key: key.to_string(),
+		value: value.to_string(),
+	}
+}
+
+impl<B> Guard<hyper::Request<B>> for Query
+{
+	fn check(&self, req: &hyper::Request<B>) -> bool
+	{
+		let query = match req.uri().query() {
+			Some(q) => q,
+			None => return false,
+		};
+
+		query.split('&').any(|pair| {
+			let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+
+			decode(key) == self.key && decode(value) == self.value
+		})
+	}
+}
+
+fn decode(s: &str) -> String
+{
+	let s = s.replace('+', " ");
+
+	percent_decode_str(&s).decode_utf8_lossy().into_owned()
+}
@@ -0,0 +1,89 @@
+/**
+# The subdomain of a wildcard host
+
+Inserted into the request extensions, when the request was matched by the router of a wildcard host
+like `*.tenant.example.com`.
+
+```rust
+use rustgram::{Request, Subdomain};
+
+pub async fn test_handler(req: Request) -> String
+{
+	match req.extensions().get::<Subdomain>() {
+		Some(subdomain) => format!("tenant: {}", subdomain.as_str()),
+		None => "no tenant".to_string(),
+	}
+}
+```
+*/
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Subdomain(String);
+
+impl Subdomain
+{
+	pub(crate) fn new(subdomain: String) -> Self
+	{
+		Self(subdomain)
+	}
+
+	pub fn as_str(&self) -> &str
+	{
+		&self.0
+	}
+}
+
+//__________________________________________________________________________________________________
+
+/**
+Match the host against the host pattern.
+
+The pattern is an exact host (`api.example.com`) or a wildcard host (`*.tenant.example.com`).
+Hosts are compared case insensitive.
+
+Returns None when the host doesn't match, otherwise the subdomain for wildcard hosts.
+*/
+pub(crate) fn match_host<'h>(pattern: &str, host: &'h str) -> Option<Option<&'h str>>
+{
+	match pattern.strip_prefix('*') {
+		Some(suffix) => {
+			//the suffix starts with the dot: .tenant.example.com
+			if host.len() <= suffix.len() {
+				return None;
+			}
+
+			let (subdomain, host_suffix) = host.split_at(host.len() - suffix.len());
+
+			if host_suffix.eq_ignore_ascii_case(suffix) {
+				Some(Some(subdomain))
+			} else {
+				None
+			}
+		},
+		None => {
+			if pattern.eq_ignore_ascii_case(host) {
+				Some(None)
+			} else {
+				None
+			}
+		},
+	}
+}
+
+/**
+Remove the port from the host header value.
+*/
+pub(crate) fn strip_port(host: &str) -> &str
+{
+	if host.starts_with('[') {
+		//ipv6
+		return match host.find(']') {
+			Some(end) => &host[..=end],
+			None => host,
+		};
+	}
+
+	match host.rfind(':') {
+		Some(i) => &host[..i],
+		None => host,
+	}
+}
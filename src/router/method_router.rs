@@ -0,0 +1,255 @@
+use std::sync::Arc;
+
+use hyper::{Method, StatusCode};
+use matchit::{InsertError, MatchError};
+
+use crate::router::guard::Guard;
+use crate::router::path::lowercase_pattern;
+use crate::router::route::MatchedRoute;
+use crate::router::{DynRoute, RouteId};
+
+/**
+# A saved route
+
+With the full path (incl. the prefix) to merge the routes into other routers.
+*/
+pub(crate) struct RouteEntry<Req, Res>
+{
+	pub(crate) pattern: String,
+	pub(crate) route: Arc<DynRoute<Req, Res>>,
+	pub(crate) name: Option<String>,
+	pub(crate) guards: Vec<Arc<dyn Guard<Req>>>,
+}
+
+impl<Req, Res> Clone for RouteEntry<Req, Res>
+{
+	fn clone(&self) -> Self
+	{
+		Self {
+			pattern: self.pattern.clone(),
+			route: self.route.clone(),
+			name: self.name.clone(),
+			guards: self.guards.clone(),
+		}
+	}
+}
+
+impl<Req, Res> RouteEntry<Req, Res>
+{
+	pub(crate) fn matched_route(&self, method: &Method) -> MatchedRoute
+	{
+		MatchedRoute::new(self.pattern.clone(), method.clone(), self.name.clone())
+	}
+
+	/**
+	Check the guards of the route.
+
+	Returns the reject status of the first guard which didn't accept the request.
+	*/
+	pub(crate) fn check(&self, req: &Req) -> Result<(), StatusCode>
+	{
+		match self.guards.iter().find(|guard| !guard.check(req)) {
+			Some(guard) => Err(guard.reject_status()),
+			None => Ok(()),
+		}
+	}
+}
+
+/**
+The params of a matched route: the name and the value from the request path
+*/
+pub(crate) type MatchedParams<'m, 'p> = Vec<(&'m str, &'p str)>;
+
+/**
+The index of the routes of one path pattern
+*/
+type SlotId = usize;
+
+/**
+# The matchit router and the routes for one method
+
+The route id is the index of the route vec.
+
+The value in the matchit router is the index of the slot vec.
+A slot contains the ids of the routes with the same path pattern.
+More than one route per pattern is only possible with guards.
+*/
+pub(crate) struct MethodRouter<Req, Res>
+{
+	pub(crate) method: Method,
+	router: matchit::Router<SlotId>,
+	/// the routes with lowercase static segments. Only set when case insensitive matching is enabled
+	lowercase_router: Option<matchit::Router<SlotId>>,
+	pub(crate) routes: Vec<RouteEntry<Req, Res>>,
+	/// the routes with guards first, then the route without guards
+	slots: Vec<Vec<RouteId>>,
+}
+
+impl<Req, Res> Clone for MethodRouter<Req, Res>
+{
+	fn clone(&self) -> Self
+	{
+		Self {
+			method: self.method.clone(),
+			router: self.router.clone(),
+			lowercase_router: self.lowercase_router.clone(),
+			routes: self.routes.clone(),
+			slots: self.slots.clone(),
+		}
+	}
+}
+
+impl<Req, Res> MethodRouter<Req, Res>
+{
+	pub(crate) fn new(method: Method) -> Self
+	{
+		Self {
+			method,
+			router: matchit::Router::new(),
+			lowercase_router: None,
+			routes: Vec::new(),
+			slots: Vec::new(),
+		}
+	}
+
+	pub(crate) fn insert(&mut self, path: String, route: Arc<DynRoute<Req, Res>>, guards: Vec<Arc<dyn Guard<Req>>>) -> Result<RouteId, InsertError>
+	{
+		let route_id = self.routes.len();
+		let guarded = !guards.is_empty();
+
+		let slot = self
+			.slots
+			.iter()
+			.position(|ids| self.routes[ids[0]].pattern == path);
+
+		match slot {
+			//only one route without guards per pattern
+			Some(slot)
+				if guarded ||
+					self.slots[slot]
+						.iter()
+						.all(|id| !self.routes[*id].guards.is_empty()) =>
+			{
+				let ids = &mut self.slots[slot];
+
+				//routes with guards are checked before the route without guards
+				let pos = if guarded {
+					ids.iter()
+						.position(|id| self.routes[*id].guards.is_empty())
+						.unwrap_or(ids.len())
+				} else {
+					ids.len()
+				};
+
+				ids.insert(pos, route_id);
+			},
+			_ => {
+				let slot = self.slots.len();
+
+				//returns the conflict error for the same pattern
+				self.router.insert(path.clone(), slot)?;
+
+				if let Some(lowercase_router) = &mut self.lowercase_router {
+					//when only the case is different to another route, the first route is matched
+					let _ = lowercase_router.insert(lowercase_pattern(&path), slot);
+				}
+
+				self.slots.push(vec![route_id]);
+			},
+		}
+
+		self.routes.push(RouteEntry {
+			pattern: path,
+			route,
+			name: None,
+			guards,
+		});
+
+		Ok(route_id)
+	}
+
+	/**
+	Build or remove the router with the lowercase static segments
+	*/
+	pub(crate) fn case_insensitive(&mut self, enable: bool)
+	{
+		if !enable {
+			self.lowercase_router = None;
+			return;
+		}
+
+		let mut lowercase_router = matchit::Router::new();
+
+		for (slot, ids) in self.slots.iter().enumerate() {
+			let _ = lowercase_router.insert(lowercase_pattern(&self.routes[ids[0]].pattern), slot);
+		}
+
+		self.lowercase_router = Some(lowercase_router);
+	}
+
+	/**
+	Match the path.
+
+	Returns the routes of the matched pattern in the order they must be checked and the params.
+	*/
+	pub(crate) fn at<'m, 'p>(
+		&'m self,
+		path: &'p str,
+	) -> Result<
+		(
+			impl Iterator<Item = &'m RouteEntry<Req, Res>> + 'm,
+			MatchedParams<'m, 'p>,
+		),
+		MatchError,
+	>
+	{
+		let err = match self.router.at(path) {
+			Ok(matched) => {
+				let params = matched.params.iter().collect();
+
+				return self.entries(*matched.value, params);
+			},
+			Err(e) => e,
+		};
+
+		let lowercase_router = match &self.lowercase_router {
+			Some(r) => r,
+			None => return Err(err),
+		};
+
+		let lowercase_path = path.to_ascii_lowercase();
+
+		let matched = lowercase_router.at(&lowercase_path)?;
+
+		//ascii lowercase keeps the byte positions, so the param values can be taken from the request path
+		let params = matched
+			.params
+			.iter()
+			.map(|(key, value)| {
+				let start = value.as_ptr() as usize - lowercase_path.as_ptr() as usize;
+
+				(key, &path[start..start + value.len()])
+			})
+			.collect();
+
+		self.entries(*matched.value, params)
+	}
+
+	fn entries<'m, 'p>(
+		&'m self,
+		slot: SlotId,
+		params: MatchedParams<'m, 'p>,
+	) -> Result<
+		(
+			impl Iterator<Item = &'m RouteEntry<Req, Res>> + 'm,
+			MatchedParams<'m, 'p>,
+		),
+		MatchError,
+	>
+	{
+		match self.slots.get(slot) {
+			Some(ids) => Ok((ids.iter().map(move |id| &self.routes[*id]), params)),
+			None => Err(MatchError::NotFound),
+		}
+	}
+}
@@ -1,20 +1,62 @@
+use std::borrow::Cow;
+use std::collections::HashMap;
 use std::future::Future;
 use std::pin::Pin;
-
-use hyper::Method;
-
-use crate::router::route::{GramRoute, Route};
-use crate::service::Service;
-use crate::RouteParams;
-
+use std::sync::Arc;
+
+use hyper::{Method, StatusCode};
+
+use crate::router::group::RouteGroup;
+use crate::router::guard::Guard;
+use crate::router::host::match_host;
+use crate::router::method_router::{MatchedParams, MethodRouter, RouteEntry};
+use crate::router::named_route::{build_url, RouteHandle};
+use crate::router::path::{is_local_path, normalize_path, toggle_trailing_slash, TrailingSlash};
+use crate::router::route::{GramRoute, IntoRoute, MatchedRoute, Route, RouteService};
+use crate::router::route_error::{ParamError, RouteError, RouteErrorKind, UrlError};
+use crate::service::{Service, ServiceTransform};
+use crate::{Request, Response, RouteParams};
+
+pub mod group;
+pub mod guard;
+pub(crate) mod host;
+mod method_router;
+pub mod named_route;
+#[cfg(feature = "serde_params")]
+mod params_de;
+pub mod path;
 pub mod route;
+pub mod route_error;
 
-type RouteVec<Req, Res> = Vec<Box<dyn Route<Req, Response = Res, Future = BoxedFut<Res>>>>;
+type DynRoute<Req, Res> = dyn Route<Req, Response = Res, Future = BoxedFut<Res>>;
 
 type RouteId = usize;
 
+/// a route to save: the method, the path with the prefix, the route and the guards
+type NewRoute<Req, Res> = (Method, String, Arc<DynRoute<Req, Res>>, Vec<Arc<dyn Guard<Req>>>);
+
+/// wraps the dispatch of the router into a middleware
+type Layer<Req, Res> = Box<dyn Fn(RouteService<Req, Res>) -> Arc<DynRoute<Req, Res>> + Send + Sync>;
+
 type BoxedFut<Res> = Pin<Box<dyn Future<Output = Res> + Send>>;
 
+/**
+The methods with an own matchit router from the start.
+
+Other methods (e.g. PROPFIND or PURGE) get their router when the first route is saved.
+*/
+const ROUTER_METHODS: [Method; 9] = [
+	Method::GET,
+	Method::POST,
+	Method::PUT,
+	Method::PATCH,
+	Method::DELETE,
+	Method::OPTIONS,
+	Method::HEAD,
+	Method::CONNECT,
+	Method::TRACE,
+];
+
 pub(crate) struct RouterMatch<'a, Req, Res>
 where
 	Req: Send + Sync + 'static,
@@ -22,6 +64,18 @@ where
 {
 	pub handler: &'a dyn Route<Req, Response = Res, Future = BoxedFut<Res>>,
 	pub params: RouteParams,
+	/// the methods which would match the path. Only set when the 405 or the auto options handler was matched
+	pub allowed: Vec<Method>,
+	/// a head request which was matched by a get route. The body of the response must be removed
+	pub strip_body: bool,
+	/// the matched route. None when no route was matched (e.g. the 404 handler)
+	pub route: Option<MatchedRoute>,
+	/// the params of the matched route can't be decoded. The handler must not be called
+	pub param_error: Option<ParamError>,
+	/// redirect to the path with or without the trailing slash. The handler must not be called
+	pub redirect: Option<(StatusCode, String)>,
+	/// the guards of all routes for the path rejected the request with this status (e.g. 415). The handler must not be called
+	pub reject: Option<StatusCode>,
 }
 
 /**
@@ -29,7 +83,7 @@ where
 
 managed and matched the request path to the routes
 
-Routes are stored as Box<Route> traits.
+Routes are stored as Arc<Route> traits.
 
 For each method there is a matchit router
 */
@@ -38,38 +92,31 @@ where
 	Req: Send + Sync + 'static,
 	Res: Send + Sync + 'static,
 {
-	get_router: matchit::Router<RouteId>,
-	post_router: matchit::Router<RouteId>,
-	put_router: matchit::Router<RouteId>,
-	patch_router: matchit::Router<RouteId>,
-	delete_router: matchit::Router<RouteId>,
-	options_router: matchit::Router<RouteId>,
-	head_router: matchit::Router<RouteId>,
-	connect_router: matchit::Router<RouteId>,
-	trace_router: matchit::Router<RouteId>,
-
-	latest_route_id_get: RouteId,
-	latest_route_id_post: RouteId,
-	latest_route_id_put: RouteId,
-	latest_route_id_patch: RouteId,
-	latest_route_id_delete: RouteId,
-	latest_route_id_option: RouteId,
-	latest_route_id_head: RouteId,
-	latest_route_id_connect: RouteId,
-	latest_route_id_trace: RouteId,
+	routers: Vec<MethodRouter<Req, Res>>,
 
 	prefix: String,
-	route_404: Box<dyn Route<Req, Response = Res, Future = BoxedFut<Res>>>,
-
-	routes_get: RouteVec<Req, Res>,
-	routes_post: RouteVec<Req, Res>,
-	routes_put: RouteVec<Req, Res>,
-	routes_patch: RouteVec<Req, Res>,
-	routes_delete: RouteVec<Req, Res>,
-	routes_options: RouteVec<Req, Res>,
-	routes_head: RouteVec<Req, Res>,
-	routes_connect: RouteVec<Req, Res>,
-	routes_trace: RouteVec<Req, Res>,
+	route_404: Box<DynRoute<Req, Res>>,
+	route_405: Option<Box<DynRoute<Req, Res>>>,
+
+	/// 404 handlers of mounted routers with their prefix
+	prefix_routes_404: Vec<(String, Box<DynRoute<Req, Res>>)>,
+
+	/// the path of the named routes
+	names: HashMap<String, String>,
+
+	/// routers for other hosts
+	hosts: Vec<(String, Router<Req, Res>)>,
+
+	/// middleware around the whole dispatch
+	layers: Vec<Layer<Req, Res>>,
+
+	auto_head: bool,
+	decode_params: bool,
+	trailing_slash: TrailingSlash,
+	normalize_path: bool,
+	case_insensitive: bool,
+	/// answers options requests without an own options route
+	route_options: Option<Box<DynRoute<Req, Res>>>,
 }
 
 impl<Req, Res> Router<Req, Res>
@@ -82,132 +129,421 @@ where
 		S: Service<Req, Output = Res>,
 	{
 		Self {
-			get_router: matchit::Router::<RouteId>::new(),
-			post_router: matchit::Router::<RouteId>::new(),
-			put_router: matchit::Router::<RouteId>::new(),
-			patch_router: matchit::Router::<RouteId>::new(),
-			delete_router: matchit::Router::<RouteId>::new(),
-			options_router: matchit::Router::<RouteId>::new(),
-			head_router: matchit::Router::<RouteId>::new(),
-			connect_router: matchit::Router::<RouteId>::new(),
-			trace_router: matchit::Router::<RouteId>::new(),
-			latest_route_id_get: 0,
-			latest_route_id_post: 0,
-			latest_route_id_put: 0,
-			latest_route_id_patch: 0,
-			latest_route_id_delete: 0,
-			latest_route_id_option: 0,
-			latest_route_id_head: 0,
-			latest_route_id_connect: 0,
-			latest_route_id_trace: 0,
+			routers: ROUTER_METHODS
+				.iter()
+				.cloned()
+				.map(MethodRouter::new)
+				.collect(),
 			prefix: "".to_string(),
 			route_404: Box::new(GramRoute::new(route_404)),
+			route_405: None,
+			prefix_routes_404: Vec::new(),
+			names: HashMap::new(),
+			hosts: Vec::new(),
+			layers: Vec::new(),
+			auto_head: false,
+			decode_params: true,
+			trailing_slash: TrailingSlash::Strict,
+			normalize_path: false,
+			case_insensitive: false,
+			route_options: None,
+		}
+	}
+
+	/**
+	# Handle head requests with the get routes
+
+	When enabled, a head request without an own head route is handled by the matching get route.
+	The body of the response is removed but the headers (incl. Content-Length) are kept.
+
+	Disabled by default.
+	*/
+	pub fn auto_head(&mut self, enable: bool)
+	{
+		self.auto_head = enable;
+	}
+
+	/**
+	# Percent decode the route params
+
+	When enabled, `/files/my%20doc.txt` got the param value `my doc.txt`.
+	Params which are not valid utf-8 after decoding are answered with a 400 response.
+
+	Enabled by default.
+	*/
+	pub fn decode_params(&mut self, enable: bool)
+	{
+		self.decode_params = enable;
+	}
+
+	/**
+	# Set how to handle paths with a wrong trailing slash
+
+	When the request path is not matched but the path with (or without) the trailing slash is matched,
+	redirect to the path of the route or call the route directly.
+
+	Strict by default.
+	*/
+	pub fn trailing_slash(&mut self, trailing_slash: TrailingSlash)
+	{
+		self.trailing_slash = trailing_slash;
+	}
+
+	/**
+	# Clean up the request path before matching
+
+	When enabled, duplicate slashes are removed and `.` and `..` segments are resolved,
+	e.g. `/users//./a/../42` is matched as `/users/42`.
+
+	Disabled by default.
+	*/
+	pub fn normalize_path(&mut self, enable: bool)
+	{
+		self.normalize_path = enable;
+	}
+
+	/**
+	# Match the static segments of the path case insensitive
+
+	When enabled, `/API/Users/Abc` is matched by the route `/api/users/:id`.
+	The param values keep their case (`id` is `Abc`).
+
+	A route with the exact case is matched first.
+	Only ascii chars are compared case insensitive.
+
+	Disabled by default.
+	*/
+	pub fn case_insensitive(&mut self, enable: bool)
+	{
+		self.case_insensitive = enable;
+
+		for router in &mut self.routers {
+			router.case_insensitive(enable);
+		}
+	}
+
+	/**
+	# Set the 405 handler
+
+	This handler is called when the path is registered but not for the method of the request.
+	The response gets an `Allow` header with the methods which are registered for this path.
+
+	The server answers with an empty `405 Method Not Allowed` response by default.
+	Without a 405 handler, a router which is not served (e.g. with own request types) uses the 404 handler.
+	*/
+	pub fn method_not_allowed<S>(&mut self, route_405: S)
+	where
+		S: Service<Req, Output = Res>,
+	{
+		self.route_405 = Some(Box::new(GramRoute::new(route_405)));
+	}
+
+	/**
+	# Apply a middleware to every request
+
+	The middleware wraps the whole dispatch of the router, so it also runs for requests
+	which are handled by the 404 handler, the 405 handler or the auto options handler,
+	e.g. for logging, request ids or cors.
+
+	Like with routes, the last added layer is called first.
+	The layers run before the route middleware.
+	Layers of mounted routers and host routers are not used.
+
+	````ignore
+	router.layer(logger_mw_transform);
+	````
+	*/
+	pub fn layer<T, S1>(&mut self, middleware: T)
+	where
+		T: ServiceTransform<RouteService<Req, Res>, Service = S1>,
+		S1: Service<Req, Output = Res>,
+	{
+		self.layers.push(Box::new(move |inner| {
+			Arc::new(GramRoute::new(middleware.transform(inner)))
+		}));
+	}
 
-			routes_get: vec![],
-			routes_post: vec![],
-			routes_put: vec![],
-			routes_patch: vec![],
-			routes_delete: vec![],
-			routes_options: vec![],
-			routes_head: vec![],
-			routes_connect: vec![],
-			routes_trace: vec![],
+	/**
+	Wrap the dispatch of the router into the layers.
+	*/
+	pub(crate) fn layered<S>(&self, dispatch: S) -> RouteService<Req, Res>
+	where
+		S: Service<Req, Output = Res>,
+	{
+		let mut route: Arc<DynRoute<Req, Res>> = Arc::new(GramRoute::new(dispatch));
+
+		for layer in &self.layers {
+			route = layer(RouteService::new(route));
 		}
+
+		RouteService::new(route)
 	}
 
 	/**
-	# save a new route as Box
+	# save a new route as Arc
 
 	For the given method.
 	Save the route in a hash map by their id
 	*/
-	pub fn insert<S: 'static>(&mut self, method: Method, path: &str, route: GramRoute<S, Req, Res>)
+	pub fn insert<R>(&mut self, method: Method, path: &str, route: R) -> RouteHandle<'_, Req, Res>
 	where
-		S: Service<Req, Output = Res>,
+		R: IntoRoute<Req, Res>,
+	{
+		match self.try_insert(method, path, route) {
+			Ok(r) => r,
+			Err(e) => panic!("{}", e),
+		}
+	}
+
+	/**
+	# save a new route as Arc
+
+	Like insert but returns an error instead of panicking,
+	when the path conflicts with another route or is not valid.
+	*/
+	pub fn try_insert<R>(&mut self, method: Method, path: &str, route: R) -> Result<RouteHandle<'_, Req, Res>, RouteError>
+	where
+		R: IntoRoute<Req, Res>,
 	{
 		let path = self.prefix.to_string() + path;
 
-		let (router, used_map, route_id) = match method {
-			Method::GET => {
-				(
-					&mut self.get_router,
-					&mut self.routes_get,
-					&mut self.latest_route_id_get,
-				)
-			},
-			Method::POST => {
-				(
-					&mut self.post_router,
-					&mut self.routes_post,
-					&mut self.latest_route_id_post,
-				)
-			},
-			Method::PUT => {
-				(
-					&mut self.put_router,
-					&mut self.routes_put,
-					&mut self.latest_route_id_put,
-				)
-			},
-			Method::DELETE => {
-				(
-					&mut self.delete_router,
-					&mut self.routes_delete,
-					&mut self.latest_route_id_delete,
-				)
-			},
-			Method::PATCH => {
-				(
-					&mut self.patch_router,
-					&mut self.routes_patch,
-					&mut self.latest_route_id_patch,
-				)
-			},
-			Method::HEAD => {
-				(
-					&mut self.head_router,
-					&mut self.routes_head,
-					&mut self.latest_route_id_head,
-				)
-			},
-			Method::OPTIONS => {
-				(
-					&mut self.options_router,
-					&mut self.routes_options,
-					&mut self.latest_route_id_option,
-				)
-			},
-			Method::CONNECT => {
-				(
-					&mut self.connect_router,
-					&mut self.routes_connect,
-					&mut self.latest_route_id_connect,
-				)
-			},
-			Method::TRACE => {
-				(
-					&mut self.trace_router,
-					&mut self.routes_trace,
-					&mut self.latest_route_id_trace,
-				)
-			},
-			_ => panic!("wrong http method"),
-		};
+		let (route, guards) = route.into_route();
+
+		let route_id = self.insert_route(method.clone(), path, Arc::new(route), guards)?;
+
+		Ok(RouteHandle::new(self, vec![(method, route_id)]))
+	}
+
+	fn insert_route(
+		&mut self,
+		method: Method,
+		path: String,
+		route: Arc<DynRoute<Req, Res>>,
+		guards: Vec<Arc<dyn Guard<Req>>>,
+	) -> Result<RouteId, RouteError>
+	{
+		if self.method_router(&method).is_none() {
+			//extension methods got their router with the first route
+			let mut router = MethodRouter::new(method.clone());
+			router.case_insensitive(self.case_insensitive);
+
+			self.routers.push(router);
+		}
+
+		let router = self.method_router_mut(&method).unwrap();
+
+		router.insert(path.clone(), route, guards).map_err(|e| {
+			RouteError {
+				method,
+				pattern: path,
+				kind: RouteErrorKind::Insert(e),
+			}
+		})
+	}
+
+	/**
+	Save all routes or none of them.
+
+	When a route can't be saved, the method routers are restored.
+	*/
+	fn insert_routes(&mut self, routes: Vec<NewRoute<Req, Res>>) -> Result<Vec<(Method, RouteId)>, RouteError>
+	{
+		let backup = self.routers.clone();
+
+		let mut ids = Vec::with_capacity(routes.len());
+
+		for (method, path, route, guards) in routes {
+			match self.insert_route(method.clone(), path, route, guards) {
+				Ok(route_id) => ids.push((method, route_id)),
+				Err(e) => {
+					self.routers = backup;
+					return Err(e);
+				},
+			}
+		}
+
+		Ok(ids)
+	}
+
+	fn name_routes(&mut self, routes: &[(Method, RouteId)], name: &str)
+	{
+		//the other routes with this name lose the name
+		for router in &mut self.routers {
+			for entry in &mut router.routes {
+				if entry.name.as_deref() == Some(name) {
+					entry.name = None;
+				}
+			}
+		}
+
+		for (method, route_id) in routes {
+			let entry = match self.method_router_mut(method) {
+				Some(router) => &mut router.routes[*route_id],
+				None => continue,
+			};
+
+			entry.name = Some(name.to_string());
+
+			let pattern = entry.pattern.clone();
+
+			self.names.insert(name.to_string(), pattern);
+		}
+	}
+
+	/**
+	# Build the url of a named route
+
+	The params are percent encoded and must match the params of the route path.
+
+	````ignore
+	router.get("/users/:id", r(user_handler)).name("user.show");
+
+	let url = router.url_for("user.show", &[("id", "42")]).unwrap();
+
+	assert_eq!(url, "/users/42");
+	````
+	*/
+	pub fn url_for(&self, name: &str, params: &[(&str, &str)]) -> Result<String, UrlError>
+	{
+		match self.names.get(name) {
+			Some(pattern) => build_url(pattern, params),
+			None => Err(UrlError::UnknownRoute(name.to_string())),
+		}
+	}
+
+	/**
+	# List all saved routes
+
+	Returns the method, the path (incl. the prefix) and the name of every route.
+	The routes are ordered by the method and then by the order they were saved.
+
+	The routes of the host routers are not included, they are listed with hosts.
+
+	````ignore
+	for (method, path, name) in router.routes() {
+		println!("{} {} {}", method, path, name.unwrap_or(""));
+	}
+
+	for (host, host_router) in router.hosts() {
+		for (method, path, name) in host_router.routes() {
+			println!("{} {}{} {}", method, host, path, name.unwrap_or(""));
+		}
+	}
+	````
+	*/
+	pub fn routes(&self) -> impl Iterator<Item = (Method, &str, Option<&str>)> + '_
+	{
+		self.routers.iter().flat_map(|router| {
+			router
+				.routes
+				.iter()
+				.map(move |entry| (router.method.clone(), entry.pattern.as_str(), entry.name.as_deref()))
+		})
+	}
+
+	/**
+	# Save a route for any http method
+
+	Also for extension methods like PROPFIND, MKCOL or PURGE.
+
+	````ignore
+	router.method(Method::from_bytes(b"PURGE").unwrap(), "/cache/:key", r(purge_handler));
+	````
+	*/
+	pub fn method<R>(&mut self, method: Method, path: &str, route: R) -> RouteHandle<'_, Req, Res>
+	where
+		R: IntoRoute<Req, Res>,
+	{
+		self.insert(method, path, route)
+	}
+
+	/**
+	# Save a route for any http method
+	or return an error if the route can't be saved
+	*/
+	pub fn try_method<R>(&mut self, method: Method, path: &str, route: R) -> Result<RouteHandle<'_, Req, Res>, RouteError>
+	where
+		R: IntoRoute<Req, Res>,
+	{
+		self.try_insert(method, path, route)
+	}
+
+	/**
+	# Save a route for many methods
 
-		router.insert(path, *route_id).unwrap();
+	The route is only build once and shared between the methods.
 
-		used_map.insert(*route_id, Box::new(route));
+	````ignore
+	router.on(&[Method::GET, Method::POST], "/webhook", r(webhook_handler).add(mw_transform));
+	````
+	*/
+	pub fn on<R>(&mut self, methods: &[Method], path: &str, route: R) -> RouteHandle<'_, Req, Res>
+	where
+		R: IntoRoute<Req, Res>,
+	{
+		match self.try_on(methods, path, route) {
+			Ok(r) => r,
+			Err(e) => panic!("{}", e),
+		}
+	}
+
+	/**
+	# Save a route for many methods
+	or return an error if the route can't be saved for one of the methods
+
+	The route is saved for all methods or for none of them.
+	*/
+	pub fn try_on<R>(&mut self, methods: &[Method], path: &str, route: R) -> Result<RouteHandle<'_, Req, Res>, RouteError>
+	where
+		R: IntoRoute<Req, Res>,
+	{
+		let path = self.prefix.to_string() + path;
+
+		let (route, guards) = route.into_route();
+
+		let route: Arc<DynRoute<Req, Res>> = Arc::new(route);
+
+		let routes = self.insert_routes(
+			methods
+				.iter()
+				.map(|method| (method.clone(), path.clone(), route.clone(), guards.clone()))
+				.collect(),
+		)?;
+
+		Ok(RouteHandle::new(self, routes))
+	}
+
+	/**
+	# Save a route for every method
+
+	get, post, put, patch, delete, options, head, connect and trace.
+	The route is only build once and shared between the methods.
+	*/
+	pub fn any<R>(&mut self, path: &str, route: R) -> RouteHandle<'_, Req, Res>
+	where
+		R: IntoRoute<Req, Res>,
+	{
+		self.on(&ROUTER_METHODS, path, route)
+	}
 
-		*route_id += 1;
+	/**
+	# Save a route for every method
+	or return an error if the route can't be saved for one of the methods
+	*/
+	pub fn try_any<R>(&mut self, path: &str, route: R) -> Result<RouteHandle<'_, Req, Res>, RouteError>
+	where
+		R: IntoRoute<Req, Res>,
+	{
+		self.try_on(&ROUTER_METHODS, path, route)
 	}
 
 	/**
 	# Save a get route
 	which is only matched by a get request
 	*/
-	pub fn get<S>(&mut self, path: &str, route: GramRoute<S, Req, Res>)
+	pub fn get<R>(&mut self, path: &str, route: R) -> RouteHandle<'_, Req, Res>
 	where
-		S: Service<Req, Output = Res>,
+		R: IntoRoute<Req, Res>,
 	{
 		self.insert(Method::GET, path, route)
 	}
@@ -216,9 +552,9 @@ where
 	# Save a post route
 	which is only matched by a post request
 	 */
-	pub fn post<S>(&mut self, path: &str, route: GramRoute<S, Req, Res>)
+	pub fn post<R>(&mut self, path: &str, route: R) -> RouteHandle<'_, Req, Res>
 	where
-		S: Service<Req, Output = Res>,
+		R: IntoRoute<Req, Res>,
 	{
 		self.insert(Method::POST, path, route)
 	}
@@ -227,9 +563,9 @@ where
 	# Save a put route
 	which is only matched by a put request
 	 */
-	pub fn put<S>(&mut self, path: &str, route: GramRoute<S, Req, Res>)
+	pub fn put<R>(&mut self, path: &str, route: R) -> RouteHandle<'_, Req, Res>
 	where
-		S: Service<Req, Output = Res>,
+		R: IntoRoute<Req, Res>,
 	{
 		self.insert(Method::PUT, path, route)
 	}
@@ -238,9 +574,9 @@ where
 	# Save a patch route
 	which is only matched by a patch request
 	 */
-	pub fn patch<S>(&mut self, path: &str, route: GramRoute<S, Req, Res>)
+	pub fn patch<R>(&mut self, path: &str, route: R) -> RouteHandle<'_, Req, Res>
 	where
-		S: Service<Req, Output = Res>,
+		R: IntoRoute<Req, Res>,
 	{
 		self.insert(Method::PATCH, path, route)
 	}
@@ -249,9 +585,9 @@ where
 	# Save a delete route
 	which is only matched by a get delete
 	 */
-	pub fn delete<S>(&mut self, path: &str, route: GramRoute<S, Req, Res>)
+	pub fn delete<R>(&mut self, path: &str, route: R) -> RouteHandle<'_, Req, Res>
 	where
-		S: Service<Req, Output = Res>,
+		R: IntoRoute<Req, Res>,
 	{
 		self.insert(Method::DELETE, path, route)
 	}
@@ -260,9 +596,9 @@ where
 	# Save an options route
 	which is only matched by an options request
 	 */
-	pub fn options<S>(&mut self, path: &str, route: GramRoute<S, Req, Res>)
+	pub fn options<R>(&mut self, path: &str, route: R) -> RouteHandle<'_, Req, Res>
 	where
-		S: Service<Req, Output = Res>,
+		R: IntoRoute<Req, Res>,
 	{
 		self.insert(Method::OPTIONS, path, route)
 	}
@@ -271,9 +607,9 @@ where
 	# Save a head route
 	which is only matched by a head request
 	 */
-	pub fn head<S>(&mut self, path: &str, route: GramRoute<S, Req, Res>)
+	pub fn head<R>(&mut self, path: &str, route: R) -> RouteHandle<'_, Req, Res>
 	where
-		S: Service<Req, Output = Res>,
+		R: IntoRoute<Req, Res>,
 	{
 		self.insert(Method::HEAD, path, route)
 	}
@@ -282,9 +618,9 @@ where
 	# Save a trace route
 	which is only matched by a trace request
 	 */
-	pub fn trace<S>(&mut self, path: &str, route: GramRoute<S, Req, Res>)
+	pub fn trace<R>(&mut self, path: &str, route: R) -> RouteHandle<'_, Req, Res>
 	where
-		S: Service<Req, Output = Res>,
+		R: IntoRoute<Req, Res>,
 	{
 		self.insert(Method::TRACE, path, route)
 	}
@@ -293,219 +629,2034 @@ where
 	# Save a get connect
 	which is only matched by a connect request
 	 */
-	pub fn connect<S>(&mut self, path: &str, route: GramRoute<S, Req, Res>)
+	pub fn connect<R>(&mut self, path: &str, route: R) -> RouteHandle<'_, Req, Res>
 	where
-		S: Service<Req, Output = Res>,
+		R: IntoRoute<Req, Res>,
 	{
 		self.insert(Method::CONNECT, path, route)
 	}
 
-	pub(crate) fn handle_req(&self, method: &Method, path: &str) -> RouterMatch<'_, Req, Res>
-	{
-		//map again because this time we don't need mut ref
-		let (router, map) = match *method {
-			Method::GET => (&self.get_router, &self.routes_get),
-			Method::POST => (&self.post_router, &self.routes_post),
-			Method::PUT => (&self.put_router, &self.routes_put),
-			Method::DELETE => (&self.delete_router, &self.routes_delete),
-			Method::PATCH => (&self.patch_router, &self.routes_patch),
-			Method::HEAD => (&self.head_router, &self.routes_head),
-			Method::OPTIONS => (&self.options_router, &self.routes_options),
-			Method::CONNECT => (&self.connect_router, &self.routes_connect),
-			Method::TRACE => (&self.trace_router, &self.routes_trace),
-			_ => {
-				return RouterMatch {
-					handler: &*self.route_404,
-					params: RouteParams::new(),
-				}
-			},
-		};
-
-		match router.at(path) {
-			Ok(r) => {
-				let params: RouteParams = r.params.into();
-
-				if let Some(route) = map.get(*r.value) {
-					RouterMatch {
-						handler: &**route,
-						params,
-					}
-				} else {
-					RouterMatch {
-						handler: &*self.route_404,
-						params: RouteParams::new(),
-					}
-				}
-			},
-			Err(_e) => {
-				RouterMatch {
-					handler: &*self.route_404,
-					params: RouteParams::new(),
-				}
-			},
-		}
-	}
-}
-
-#[cfg(test)]
-mod test
-{
-	use std::sync::Arc;
-
-	use futures::StreamExt;
-
-	use super::*;
-	use crate::{r, Request, Response};
-
-	async fn test_handler(_req: Request) -> String
+	/**
+	# Save a get route
+	or return an error if the route can't be saved
+	*/
+	pub fn try_get<R>(&mut self, path: &str, route: R) -> Result<RouteHandle<'_, Req, Res>, RouteError>
+	where
+		R: IntoRoute<Req, Res>,
 	{
-		"test".to_string()
+		self.try_insert(Method::GET, path, route)
 	}
 
-	async fn test_handler_param(req: Request) -> String
+	/**
+	# Save a post route
+	or return an error if the route can't be saved
+	*/
+	pub fn try_post<R>(&mut self, path: &str, route: R) -> Result<RouteHandle<'_, Req, Res>, RouteError>
+	where
+		R: IntoRoute<Req, Res>,
 	{
-		let params = req.extensions().get::<RouteParams>().unwrap();
-
-		format!("test_param: {}", params.get("id").unwrap())
+		self.try_insert(Method::POST, path, route)
 	}
 
-	async fn test_handler_all(_req: Request) -> String
+	/**
+	# Save a put route
+	or return an error if the route can't be saved
+	*/
+	pub fn try_put<R>(&mut self, path: &str, route: R) -> Result<RouteHandle<'_, Req, Res>, RouteError>
+	where
+		R: IntoRoute<Req, Res>,
 	{
-		"test_all".to_string()
+		self.try_insert(Method::PUT, path, route)
 	}
 
-	async fn test_handler_result(_req: Request) -> Result<String, String>
+	/**
+	# Save a patch route
+	or return an error if the route can't be saved
+	*/
+	pub fn try_patch<R>(&mut self, path: &str, route: R) -> Result<RouteHandle<'_, Req, Res>, RouteError>
+	where
+		R: IntoRoute<Req, Res>,
 	{
-		Ok("test".to_string())
+		self.try_insert(Method::PATCH, path, route)
 	}
 
-	pub struct TestMw<S>
+	/**
+	# Save a delete route
+	or return an error if the route can't be saved
+	*/
+	pub fn try_delete<R>(&mut self, path: &str, route: R) -> Result<RouteHandle<'_, Req, Res>, RouteError>
+	where
+		R: IntoRoute<Req, Res>,
 	{
-		inner: Arc<S>,
+		self.try_insert(Method::DELETE, path, route)
 	}
 
-	impl<S> Service<Request> for TestMw<S>
-	where
+	/**
+	# Save an options route
+	or return an error if the route can't be saved
+	*/
+	pub fn try_options<R>(&mut self, path: &str, route: R) -> Result<RouteHandle<'_, Req, Res>, RouteError>
+	where
+		R: IntoRoute<Req, Res>,
+	{
+		self.try_insert(Method::OPTIONS, path, route)
+	}
+
+	/**
+	# Save a head route
+	or return an error if the route can't be saved
+	*/
+	pub fn try_head<R>(&mut self, path: &str, route: R) -> Result<RouteHandle<'_, Req, Res>, RouteError>
+	where
+		R: IntoRoute<Req, Res>,
+	{
+		self.try_insert(Method::HEAD, path, route)
+	}
+
+	/**
+	# Save a trace route
+	or return an error if the route can't be saved
+	*/
+	pub fn try_trace<R>(&mut self, path: &str, route: R) -> Result<RouteHandle<'_, Req, Res>, RouteError>
+	where
+		R: IntoRoute<Req, Res>,
+	{
+		self.try_insert(Method::TRACE, path, route)
+	}
+
+	/**
+	# Save a connect route
+	or return an error if the route can't be saved
+	*/
+	pub fn try_connect<R>(&mut self, path: &str, route: R) -> Result<RouteHandle<'_, Req, Res>, RouteError>
+	where
+		R: IntoRoute<Req, Res>,
+	{
+		self.try_insert(Method::CONNECT, path, route)
+	}
+
+	/**
+	# Create a group of routes
+
+	All routes which are added to the router inside the closure are prefixed with the group prefix.
+
+	Middleware for all routes of the group can be applied to the returned group.
+	Like in the route builder, the route middleware is called first, then the group middleware
+	and then the middleware of the parent groups.
+
+	Groups can be nested, the prefixes are concatenated.
+
+	````ignore
+	router.group("/admin", |g| {
+		g.get("/user/:id", r(user_handler));
+
+		g.group("/management", |g| {
+			g.put("/put", r(put_handler).add(route_mw));
+		})
+		.add(management_mw);
+	})
+	.add(auth_mw);
+	````
+	*/
+	pub fn group<F>(&mut self, prefix: &str, f: F) -> RouteGroup<'_, Req, Res>
+	where
+		F: FnOnce(&mut Self),
+	{
+		let parent_prefix = self.prefix.clone();
+		let start = self.route_counts();
+
+		self.prefix = parent_prefix.clone() + prefix;
+
+		f(self);
+
+		self.prefix = parent_prefix;
+
+		//the route ids are the index of the route vec, so the new routes are between the old and the new length.
+		//routers of extension methods can be created inside the group, they start with 0 routes
+		let routes = self
+			.route_counts()
+			.into_iter()
+			.map(|(method, end)| {
+				let start = start
+					.iter()
+					.find(|(m, _)| *m == method)
+					.map_or(0, |(_, start)| *start);
+
+				(method, start..end)
+			})
+			.collect();
+
+		RouteGroup::new(self, routes)
+	}
+
+	/**
+	# Mount another router under the prefix
+
+	All routes of the other router are added with the prefix to this router.
+	The 404 handler of the other router is not used.
+	*/
+	pub fn mount(&mut self, prefix: &str, router: Router<Req, Res>)
+	{
+		if let Err(e) = self.try_mount(prefix, router) {
+			panic!("{}", e);
+		}
+	}
+
+	/**
+	# Mount another router under the prefix
+	or return an error if a route of the other router can't be saved
+
+	All routes of the other router are saved or none of them.
+	*/
+	pub fn try_mount(&mut self, prefix: &str, router: Router<Req, Res>) -> Result<(), RouteError>
+	{
+		self.merge(prefix, router, false)
+	}
+
+	/**
+	# Mount another router under the prefix and keep its 404 handler
+
+	Like mount but requests to a path under the prefix, which are not matched,
+	are handled by the 404 handler of the other router.
+	*/
+	pub fn mount_with_404(&mut self, prefix: &str, router: Router<Req, Res>)
+	{
+		if let Err(e) = self.try_mount_with_404(prefix, router) {
+			panic!("{}", e);
+		}
+	}
+
+	/**
+	# Mount another router under the prefix and keep its 404 handler
+	or return an error if a route of the other router can't be saved
+	*/
+	pub fn try_mount_with_404(&mut self, prefix: &str, router: Router<Req, Res>) -> Result<(), RouteError>
+	{
+		self.merge(prefix, router, true)
+	}
+
+	fn merge(&mut self, prefix: &str, router: Router<Req, Res>, keep_404: bool) -> Result<(), RouteError>
+	{
+		let prefix = self.prefix.to_string() + prefix;
+
+		let mut routes = Vec::new();
+		let mut names = Vec::new();
+
+		for method_router in router.routers {
+			for entry in method_router.routes {
+				names.push(entry.name);
+				routes.push((
+					method_router.method.clone(),
+					prefix.clone() + &entry.pattern,
+					entry.route,
+					entry.guards,
+				));
+			}
+		}
+
+		let routes = self.insert_routes(routes)?;
+
+		//routes which are saved for many methods share the name
+		let mut named: Vec<(String, Vec<(Method, RouteId)>)> = Vec::new();
+
+		for (route, name) in routes.into_iter().zip(names) {
+			if let Some(name) = name {
+				match named.iter_mut().find(|(n, _)| *n == name) {
+					Some((_, routes)) => routes.push(route),
+					None => named.push((name, vec![route])),
+				}
+			}
+		}
+
+		for (name, routes) in named {
+			self.name_routes(&routes, &name);
+		}
+
+		//nested mounted routers
+		for (nested_prefix, route_404) in router.prefix_routes_404 {
+			self.prefix_routes_404
+				.push((prefix.clone() + &nested_prefix, route_404));
+		}
+
+		if keep_404 {
+			self.prefix_routes_404.push((prefix, router.route_404));
+		}
+
+		Ok(())
+	}
+
+	/**
+	# Use another router for a host
+
+	Requests to this host (from the uri or the Host header) are matched by the other router.
+	Requests to other hosts are matched by this router.
+
+	Wildcard subdomains like `*.tenant.example.com` are possible.
+	The subdomain is inserted into the request extensions as `Subdomain`.
+	Exact hosts are matched first, then the wildcard host with the longest suffix.
+
+	````ignore
+	router.host("api.example.com", api_router);
+	router.host("*.tenant.example.com", tenant_router);
+	````
+	*/
+	pub fn host(&mut self, host: &str, router: Router<Req, Res>)
+	{
+		self.hosts.push((host.to_string(), router));
+	}
+
+	/**
+	# List the host routers
+
+	Returns the host pattern and the router in the order they were added.
+	*/
+	pub fn hosts(&self) -> impl Iterator<Item = (&str, &Router<Req, Res>)> + '_
+	{
+		self.hosts
+			.iter()
+			.map(|(host, router)| (host.as_str(), router))
+	}
+
+	/**
+	Get the router for the host.
+
+	Returns the router and the subdomain of a wildcard host or None if no host router matched.
+	*/
+	pub(crate) fn route_host<'h>(&self, host: &'h str) -> Option<(&Router<Req, Res>, Option<&'h str>)>
+	{
+		self.hosts
+			.iter()
+			.filter_map(|(pattern, router)| match_host(pattern, host).map(|subdomain| (pattern, router, subdomain)))
+			.max_by_key(|(pattern, _, subdomain)| (subdomain.is_none(), pattern.len()))
+			.map(|(_, router, subdomain)| (router, subdomain))
+	}
+
+	fn route_counts(&self) -> Vec<(Method, RouteId)>
+	{
+		self.routers
+			.iter()
+			.map(|r| (r.method.clone(), r.routes.len()))
+			.collect()
+	}
+
+	fn method_router(&self, method: &Method) -> Option<&MethodRouter<Req, Res>>
+	{
+		self.routers.iter().find(|r| r.method == *method)
+	}
+
+	pub(crate) fn method_router_mut(&mut self, method: &Method) -> Option<&mut MethodRouter<Req, Res>>
+	{
+		self.routers.iter_mut().find(|r| r.method == *method)
+	}
+
+	/**
+	Look up all method routers for the path.
+
+	Returns every method which got a route for this path.
+	For the path `*` every method with at least one route is returned.
+	*/
+	fn path_methods(&self, path: &str) -> Vec<Method>
+	{
+		let mut allowed: Vec<Method> = self
+			.routers
+			.iter()
+			.filter(
+				|r| {
+					if path == "*" {
+						!r.routes.is_empty()
+					} else {
+						r.at(path).is_ok()
+					}
+				},
+			)
+			.map(|r| r.method.clone())
+			.collect();
+
+		if allowed.is_empty() {
+			return allowed;
+		}
+
+		if self.auto_head && allowed.contains(&Method::GET) && !allowed.contains(&Method::HEAD) {
+			allowed.push(Method::HEAD);
+		}
+
+		if self.route_options.is_some() && !allowed.contains(&Method::OPTIONS) {
+			allowed.push(Method::OPTIONS);
+		}
+
+		allowed
+	}
+
+	/**
+	Look up the other method routers for the path.
+
+	Returns every other method which got a route for this path.
+	*/
+	fn allowed_methods(&self, method: &Method, path: &str) -> Vec<Method>
+	{
+		let mut allowed = self.path_methods(path);
+
+		allowed.retain(|m| m != method);
+
+		allowed
+	}
+
+	/**
+	The 404 handler for this path.
+
+	When the path is under the prefix of a mounted router with an own 404 handler, use this handler.
+	The longest prefix wins.
+	*/
+	fn route_404(&self, path: &str) -> &DynRoute<Req, Res>
+	{
+		self.prefix_routes_404
+			.iter()
+			.filter(|(prefix, _)| {
+				match path.strip_prefix(prefix.as_str()) {
+					Some(rest) => rest.is_empty() || rest.starts_with('/') || prefix.ends_with('/'),
+					None => false,
+				}
+			})
+			.max_by_key(|(prefix, _)| prefix.len())
+			.map_or(&*self.route_404, |(_, route_404)| &**route_404)
+	}
+
+	/**
+	No route found for this method.
+
+	Call the 405 handler if the path is registered for other methods, otherwise the 404 handler.
+	*/
+	fn not_found(&self, method: &Method, path: &str) -> RouterMatch<'_, Req, Res>
+	{
+		if let Some(route_405) = &self.route_405 {
+			let allowed = self.allowed_methods(method, path);
+
+			if !allowed.is_empty() {
+				return RouterMatch {
+					handler: &**route_405,
+					params: RouteParams::new(),
+					allowed,
+					strip_body: false,
+					route: None,
+					param_error: None,
+					redirect: None,
+					reject: None,
+				};
+			}
+		}
+
+		RouterMatch {
+			handler: self.route_404(path),
+			params: RouteParams::new(),
+			allowed: Vec::new(),
+			strip_body: false,
+			route: None,
+			param_error: None,
+			redirect: None,
+			reject: None,
+		}
+	}
+
+	/**
+	Match the request.
+
+	The method and the path are from the request, the request itself is only used for the guards.
+	*/
+	pub(crate) fn handle_req(&self, method: &Method, path: &str, req: &Req) -> RouterMatch<'_, Req, Res>
+	{
+		let path = if self.normalize_path {
+			normalize_path(path)
+		} else {
+			Cow::Borrowed(path)
+		};
+
+		let reject = match self.match_route(method, &path, req) {
+			Ok(matched) => return matched,
+			Err(reject) => reject,
+		};
+
+		if let Some(status) = reject {
+			//the path got routes but the guards rejected the request
+			return RouterMatch {
+				handler: self.route_404(&path),
+				params: RouteParams::new(),
+				allowed: Vec::new(),
+				strip_body: false,
+				route: None,
+				param_error: None,
+				redirect: None,
+				reject: if status == StatusCode::NOT_FOUND { None } else { Some(status) },
+			};
+		}
+
+		if self.trailing_slash != TrailingSlash::Strict {
+			if let Some(other_path) = toggle_trailing_slash(&path) {
+				if let Ok(matched) = self.match_route(method, &other_path, req) {
+					let status = match self.trailing_slash {
+						TrailingSlash::Redirect301 => StatusCode::MOVED_PERMANENTLY,
+						TrailingSlash::Redirect308 => StatusCode::PERMANENT_REDIRECT,
+						_ => return matched,
+					};
+
+					if !is_local_path(&other_path) {
+						//e.g. `//evil.com/` would redirect to another host
+						return self.not_found(method, &path);
+					}
+
+					return RouterMatch {
+						handler: self.route_404(&path),
+						params: RouteParams::new(),
+						allowed: Vec::new(),
+						strip_body: false,
+						route: None,
+						param_error: None,
+						redirect: Some((status, other_path)),
+						reject: None,
+					};
+				}
+			}
+		}
+
+		//the allowed methods of the path with or without the trailing slash
+		let path = self.methods_path(path);
+
+		if let (Some(route_options), &Method::OPTIONS) = (&self.route_options, method) {
+			let allowed = self.path_methods(&path);
+
+			if !allowed.is_empty() {
+				return RouterMatch {
+					handler: &**route_options,
+					params: RouteParams::new(),
+					allowed,
+					strip_body: false,
+					route: None,
+					param_error: None,
+					redirect: None,
+					reject: None,
+				};
+			}
+		}
+
+		self.not_found(method, &path)
+	}
+
+	/**
+	The path to look up the allowed methods.
+
+	When the path got no routes but the path with (or without) the trailing slash got routes
+	and the trailing slash is not strict, the other path is used.
+	So the trailing slash doesn't change the 405 and the auto options response.
+	*/
+	fn methods_path<'p>(&self, path: Cow<'p, str>) -> Cow<'p, str>
+	{
+		if self.trailing_slash == TrailingSlash::Strict || !self.path_methods(&path).is_empty() {
+			return path;
+		}
+
+		match toggle_trailing_slash(&path) {
+			Some(other_path) if is_local_path(&other_path) && !self.path_methods(&other_path).is_empty() => Cow::Owned(other_path),
+			_ => path,
+		}
+	}
+
+	/**
+	Match the route of the method or the get route for head requests (when auto head is enabled).
+
+	Returns the reject status of the guards when the path got routes but no route accepted the request.
+	*/
+	fn match_route(&self, method: &Method, path: &str, req: &Req) -> Result<RouterMatch<'_, Req, Res>, Option<StatusCode>>
+	{
+		let matched = self
+			.method_router(method)
+			.and_then(|router| router.at(path).ok());
+
+		if let Some((entries, params)) = matched {
+			let entry = Self::guarded_entry(entries, req)?;
+
+			return Ok(self.route_match(entry, method, params, false));
+		}
+
+		if self.auto_head && *method == Method::HEAD {
+			let matched = self
+				.method_router(&Method::GET)
+				.and_then(|router| router.at(path).ok());
+
+			if let Some((entries, params)) = matched {
+				let entry = Self::guarded_entry(entries, req)?;
+
+				return Ok(self.route_match(entry, &Method::GET, params, true));
+			}
+		}
+
+		Err(None)
+	}
+
+	/**
+	The first route which guards accepted the request.
+
+	When no route accepted the request, a reject status other than not found wins (e.g. 415 of the content type guard).
+	*/
+	fn guarded_entry<'a>(entries: impl Iterator<Item = &'a RouteEntry<Req, Res>>, req: &Req) -> Result<&'a RouteEntry<Req, Res>, Option<StatusCode>>
+	{
+		let mut reject = StatusCode::NOT_FOUND;
+
+		for entry in entries {
+			match entry.check(req) {
+				Ok(_) => return Ok(entry),
+				Err(status) => {
+					if reject == StatusCode::NOT_FOUND {
+						reject = status;
+					}
+				},
+			}
+		}
+
+		Err(Some(reject))
+	}
+
+	fn route_match<'a>(
+		&'a self,
+		entry: &'a RouteEntry<Req, Res>,
+		method: &Method,
+		params: MatchedParams,
+		strip_body: bool,
+	) -> RouterMatch<'a, Req, Res>
+	{
+		let (params, param_error) = if self.decode_params {
+			match RouteParams::decode(params) {
+				Ok(params) => (params, None),
+				Err(e) => (RouteParams::new(), Some(e)),
+			}
+		} else {
+			(params.into_iter().collect(), None)
+		};
+
+		RouterMatch {
+			handler: &*entry.route,
+			params,
+			allowed: Vec::new(),
+			strip_body,
+			route: Some(entry.matched_route(method)),
+			param_error,
+			redirect: None,
+			reject: None,
+		}
+	}
+}
+
+impl Router<Request, Response>
+{
+	/**
+	# Answer options requests with the allowed methods
+
+	When enabled, an options request without an own options route is answered with `204 No Content`
+	and an `Allow` header with every method which got a route for the path.
+
+	`OPTIONS *` lists every method of the router.
+
+	Disabled by default.
+	*/
+	pub fn auto_options(&mut self, enable: bool)
+	{
+		self.route_options = if enable {
+			Some(Box::new(GramRoute::new(|_req: Request| {
+				async {
+					hyper::Response::builder()
+						.status(StatusCode::NO_CONTENT)
+						.body(hyper::Body::empty())
+						.unwrap()
+				}
+			})))
+		} else {
+			None
+		};
+	}
+
+	/**
+	Answer with an empty `405 Method Not Allowed` response, when no 405 handler was set.
+
+	Also for the routers of the hosts.
+	*/
+	pub(crate) fn default_method_not_allowed(&mut self)
+	{
+		if self.route_405.is_none() {
+			self.method_not_allowed(|_req: Request| {
+				async {
+					hyper::Response::builder()
+						.status(StatusCode::METHOD_NOT_ALLOWED)
+						.body(hyper::Body::empty())
+						.unwrap()
+				}
+			});
+		}
+
+		for (_, router) in &mut self.hosts {
+			router.default_method_not_allowed();
+		}
+	}
+}
+
+#[cfg(test)]
+mod test
+{
+	use std::sync::Arc;
+
+	use futures::StreamExt;
+
+	use super::*;
+	use crate::r;
+	use crate::router::path::TrailingSlash;
+	use crate::service::{IntoResponse, ServiceTransform};
+
+	async fn test_handler(_req: Request) -> String
+	{
+		"test".to_string()
+	}
+
+	async fn test_handler_param(req: Request) -> String
+	{
+		let params = req.extensions().get::<RouteParams>().unwrap();
+
+		format!("test_param: {}", params.get("id").unwrap())
+	}
+
+	async fn test_handler_all(_req: Request) -> String
+	{
+		"test_all".to_string()
+	}
+
+	async fn test_handler_result(_req: Request) -> Result<String, String>
+	{
+		Ok("test".to_string())
+	}
+
+	pub struct TestMw<S>
+	{
+		inner: Arc<S>,
+	}
+
+	impl<S> Service<Request> for TestMw<S>
+	where
+		S: Service<Request, Output = Response>,
+	{
+		type Output = S::Output;
+
+		fn call(&self, req: Request) -> impl Future<Output = Self::Output> + Send + 'static
+		{
+			let next = self.inner.clone();
+
+			async move {
+				//do something before req
+
+				next.call(req).await
+
+				//do something after
+			}
+		}
+	}
+
+	fn test_mw_transform<S>(s: S) -> TestMw<S>
+	{
+		TestMw {
+			inner: Arc::new(s),
+		}
+	}
+
+	/**
+	Mw without arc
+	*/
+	pub struct TestMw1<S>
+	{
+		inner: S,
+	}
+
+	impl<S> Service<Request> for TestMw1<S>
+	where
+		S: Service<Request, Output = Response>,
+	{
+		type Output = S::Output;
+
+		#[allow(clippy::redundant_async_block)]
+		fn call(&self, req: Request) -> impl Future<Output = Self::Output> + Send + 'static
+		{
+			let res = self.inner.call(req);
+
+			async move {
+				//do something before req
+
+				res.await
+
+				//do something after
+			}
+		}
+	}
+
+	fn test_mw_transform1<S>(s: S) -> TestMw1<S>
+	{
+		TestMw1 {
+			inner: s,
+		}
+	}
+
+	#[tokio::test]
+	async fn test_adding_routes_and_match()
+	{
+		let mut router: Router<Request, Response> = Router::new(|_req: Request| async { "404".to_string() });
+
+		router.get("/test", r(test_handler).add(test_mw_transform));
+
+		router.post("/test/:id", r(test_handler_param).add(test_mw_transform1));
+
+		router.get("/test/all/*a", r(test_handler_all));
+
+		router.post("/test1/:id", r(test_handler_param).add(test_mw_transform1));
+
+		//match all
+		let handler = router.handle_req(&Method::GET, "/test/all/abcdefg", &Request::default());
+
+		let res = handler
+			.handler
+			.invoke(Request::new(hyper::Body::from("")))
+			.await;
+
+		let res_body = res.into_body().next().await.unwrap().unwrap();
+
+		assert_eq!(res_body, "test_all");
+		assert_eq!("abcdefg", handler.params.get("a").unwrap());
+
+		//match with url param
+		let handler = router.handle_req(&Method::POST, "/test/abcdefg", &Request::default());
+
+		let mut req = Request::new(hyper::Body::from(""));
+		req.extensions_mut().insert(handler.params);
+
+		let res = handler.handler.invoke(req).await;
+
+		let res_body = res.into_body().next().await.unwrap().unwrap();
+
+		assert_eq!(res_body, "test_param: abcdefg");
+	}
+
+	#[tokio::test]
+	async fn test_result_handler()
+	{
+		let mut router: Router<Request, Response> = Router::new(|_req: Request| async { "404".to_string() });
+
+		router.get("/test", r(test_handler_result));
+
+		router.get("/test/:id", r(test_handler_result));
+
+		router.get("/test/all/*a", r(test_handler_result));
+
+		let handler = router.handle_req(&Method::GET, "/test/all/abcdefg", &Request::default());
+
+		let res = handler
+			.handler
+			.invoke(Request::new(hyper::Body::from("")))
+			.await;
+
+		let res_body = res.into_body().next().await.unwrap().unwrap();
+
+		assert_eq!(res_body, "test");
+		assert_eq!("abcdefg", handler.params.get("a").unwrap());
+	}
+
+	#[tokio::test]
+	async fn test_method_not_allowed()
+	{
+		let mut router: Router<Request, Response> = Router::new(|_req: Request| async { "404".to_string() });
+
+		router.get("/test/:id", r(test_handler));
+		router.put("/test/:id", r(test_handler));
+
+		//without 405 handler the 404 handler is used
+		let handler = router.handle_req(&Method::POST, "/test/abc", &Request::default());
+
+		assert!(handler.allowed.is_empty());
+
+		router.method_not_allowed(|_req: Request| async { "405".to_string() });
+
+		let handler = router.handle_req(&Method::POST, "/test/abc", &Request::default());
+
+		let res = handler
+			.handler
+			.invoke(Request::new(hyper::Body::from("")))
+			.await;
+
+		let res_body = res.into_body().next().await.unwrap().unwrap();
+
+		assert_eq!(res_body, "405");
+		assert_eq!(handler.allowed, vec![Method::GET, Method::PUT]);
+
+		//unknown path is still a 404
+		let handler = router.handle_req(&Method::POST, "/abc", &Request::default());
+
+		let res = handler
+			.handler
+			.invoke(Request::new(hyper::Body::from("")))
+			.await;
+
+		let res_body = res.into_body().next().await.unwrap().unwrap();
+
+		assert_eq!(res_body, "404");
+		assert!(handler.allowed.is_empty());
+	}
+
+	pub struct HeaderMw<S>
+	{
+		inner: Arc<S>,
+		name: &'static str,
+	}
+
+	impl<S> Service<Request> for HeaderMw<S>
+	where
+		S: Service<Request, Output = Response>,
+	{
+		type Output = S::Output;
+
+		fn call(&self, req: Request) -> impl Future<Output = Self::Output> + Send + 'static
+		{
+			let next = self.inner.clone();
+			let name = self.name;
+
+			async move {
+				let mut res = next.call(req).await;
+
+				//save the order of the called middleware
+				res.headers_mut().append("x-mw", name.parse().unwrap());
+
+				res
+			}
+		}
+	}
+
+	pub struct HeaderMwTransform(&'static str);
+
+	impl<S> ServiceTransform<S> for HeaderMwTransform
+	where
 		S: Service<Request, Output = Response>,
 	{
-		type Output = S::Output;
+		type Service = HeaderMw<S>;
+
+		fn transform(&self, inner: S) -> Self::Service
+		{
+			HeaderMw {
+				inner: Arc::new(inner),
+				name: self.0,
+			}
+		}
+	}
+
+	#[tokio::test]
+	async fn test_group()
+	{
+		let mut router: Router<Request, Response> = Router::new(|_req: Request| async { "404".to_string() });
+
+		router
+			.group("/admin", |g| {
+				g.get("/user/:id", r(test_handler_param));
+
+				g.group("/management", |g| {
+					g.put("/put", r(test_handler).add(HeaderMwTransform("route")));
+				})
+				.add(HeaderMwTransform("management"));
+			})
+			.add(HeaderMwTransform("admin1"))
+			.add(HeaderMwTransform("admin"));
+
+		router.get("/user/:id", r(test_handler_param));
+
+		let handler = router.handle_req(&Method::PUT, "/admin/management/put", &Request::default());
+
+		let res = handler
+			.handler
+			.invoke(Request::new(hyper::Body::from("")))
+			.await;
+
+		let mw = res
+			.headers()
+			.get_all("x-mw")
+			.iter()
+			.map(|h| h.to_str().unwrap())
+			.collect::<Vec<_>>();
+
+		assert_eq!(mw, vec!["route", "management", "admin1", "admin"]);
+
+		let handler = router.handle_req(&Method::GET, "/admin/user/abc", &Request::default());
+
+		let mut req = Request::new(hyper::Body::from(""));
+		req.extensions_mut().insert(handler.params);
+
+		let res = handler.handler.invoke(req).await;
+
+		assert_eq!(res.headers().get_all("x-mw").iter().count(), 2);
+
+		let res_body = res.into_body().next().await.unwrap().unwrap();
+
+		assert_eq!(res_body, "test_param: abc");
+
+		//the prefix is only used inside the group
+		let handler = router.handle_req(&Method::GET, "/user/abc", &Request::default());
+
+		let mut req = Request::new(hyper::Body::from(""));
+		req.extensions_mut().insert(handler.params);
+
+		let res = handler.handler.invoke(req).await;
+
+		assert!(res.headers().get("x-mw").is_none());
+	}
+
+	#[test]
+	fn test_group_shared_route()
+	{
+		let mut router: Router<Request, Response> = Router::new(|_req: Request| async { "404".to_string() });
+
+		let transforms = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+		let counter = transforms.clone();
+
+		router
+			.group("/admin", |g| {
+				g.any("/all", r(test_handler));
+			})
+			.add(move |inner: RouteService<Request, Response>| {
+				counter.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+
+				inner
+			});
+
+		assert_eq!(transforms.load(std::sync::atomic::Ordering::SeqCst), 1);
+
+		//the same route instance for all methods
+		let get = router
+			.handle_req(&Method::GET, "/admin/all", &Request::default())
+			.handler as *const _ as *const ();
+		let put = router
+			.handle_req(&Method::PUT, "/admin/all", &Request::default())
+			.handler as *const _ as *const ();
+
+		assert_eq!(get, put);
+	}
+
+	#[tokio::test]
+	async fn test_group_extension_method()
+	{
+		let mut router: Router<Request, Response> = Router::new(|_req: Request| async { "404".to_string() });
+
+		let purge = Method::from_bytes(b"PURGE").unwrap();
+
+		router
+			.group("/admin", |g| {
+				g.get("/x", r(test_handler));
+				g.method(purge.clone(), "/x", r(test_handler));
+			})
+			.add(HeaderMwTransform("admin"));
+
+		for method in [Method::GET, purge] {
+			let handler = router.handle_req(&method, "/admin/x", &Request::default());
+
+			let res = handler
+				.handler
+				.invoke(Request::new(hyper::Body::from("")))
+				.await;
+
+			assert_eq!(res.headers().get("x-mw").unwrap(), "admin");
+		}
+	}
+
+	#[tokio::test]
+	async fn test_mount()
+	{
+		let mut router: Router<Request, Response> = Router::new(|_req: Request| async { "404".to_string() });
+
+		let mut users: Router<Request, Response> = Router::new(|_req: Request| async { "users 404".to_string() });
+
+		users.get("/:id", r(test_handler_param));
+		users.group("/all", |g| {
+			g.post("", r(test_handler_all));
+		});
+
+		let mut billing: Router<Request, Response> = Router::new(|_req: Request| async { "billing 404".to_string() });
+
+		billing.get("/test", r(test_handler));
+
+		router.mount_with_404("/users", users);
+		router.mount("/billing", billing);
+
+		let handler = router.handle_req(&Method::GET, "/users/abc", &Request::default());
+
+		let mut req = Request::new(hyper::Body::from(""));
+		req.extensions_mut().insert(handler.params);
+
+		let res = handler.handler.invoke(req).await;
+
+		let res_body = res.into_body().next().await.unwrap().unwrap();
+
+		assert_eq!(res_body, "test_param: abc");
+
+		let handler = router.handle_req(&Method::POST, "/users/all", &Request::default());
+
+		let res = handler
+			.handler
+			.invoke(Request::new(hyper::Body::from("")))
+			.await;
+
+		let res_body = res.into_body().next().await.unwrap().unwrap();
+
+		assert_eq!(res_body, "test_all");
+
+		//the 404 handler of the mounted router
+		let handler = router.handle_req(&Method::GET, "/users/abc/def", &Request::default());
+
+		let res = handler
+			.handler
+			.invoke(Request::new(hyper::Body::from("")))
+			.await;
+
+		let res_body = res.into_body().next().await.unwrap().unwrap();
+
+		assert_eq!(res_body, "users 404");
+
+		//not under the prefix of the mounted router
+		let handler = router.handle_req(&Method::GET, "/usersabc", &Request::default());
+
+		let res = handler
+			.handler
+			.invoke(Request::new(hyper::Body::from("")))
+			.await;
+
+		let res_body = res.into_body().next().await.unwrap().unwrap();
+
+		assert_eq!(res_body, "404");
+
+		//mounted without the 404 handler
+		let handler = router.handle_req(&Method::GET, "/billing/abc", &Request::default());
+
+		let res = handler
+			.handler
+			.invoke(Request::new(hyper::Body::from("")))
+			.await;
+
+		let res_body = res.into_body().next().await.unwrap().unwrap();
+
+		assert_eq!(res_body, "404");
+	}
+
+	#[test]
+	fn test_try_insert()
+	{
+		let mut router: Router<Request, Response> = Router::new(|_req: Request| async { "404".to_string() });
+
+		router.try_get("/test/:id", r(test_handler)).unwrap();
+
+		let mut err = None;
+
+		router.group("/admin", |g| {
+			g.try_post("/test/:id", r(test_handler)).unwrap();
+
+			err = g.try_post("/test/:name", r(test_handler)).err();
+		});
+
+		assert_eq!(
+			err,
+			Some(RouteError {
+				method: Method::POST,
+				pattern: "/admin/test/:name".to_string(),
+				kind: RouteErrorKind::Insert(matchit::InsertError::Conflict {
+					with: "/admin/test/:id".to_string()
+				}),
+			})
+		);
+	}
+
+	#[test]
+	fn test_try_on_and_mount()
+	{
+		let mut router: Router<Request, Response> = Router::new(|_req: Request| async { "404".to_string() });
+
+		router.post("/test/:id", r(test_handler));
+
+		//the post route conflicts, so the get route is not saved too
+		let err = router
+			.try_on(&[Method::GET, Method::POST], "/test/:name", r(test_handler))
+			.err()
+			.unwrap();
+
+		assert_eq!(err.method, Method::POST);
+		assert_eq!(router.routes().count(), 1);
+		assert!(router
+			.handle_req(&Method::GET, "/test/abc", &Request::default())
+			.route
+			.is_none());
+
+		assert!(router.try_any("/test/:name", r(test_handler)).is_err());
+		assert!(router
+			.try_method(Method::from_bytes(b"PURGE").unwrap(), "/test/:name", r(test_handler))
+			.is_ok());
+
+		let mut users: Router<Request, Response> = Router::new(|_req: Request| async { "404".to_string() });
+
+		users.get("/all", r(test_handler)).name("users.all");
+		users.post("/:id", r(test_handler));
+
+		let mut app: Router<Request, Response> = Router::new(|_req: Request| async { "404".to_string() });
+
+		app.post("/users/:name", r(test_handler));
+
+		assert!(app.try_mount("/users", users).is_err());
+		assert_eq!(app.routes().count(), 1);
+		assert!(app.url_for("users.all", &[]).is_err());
+	}
+
+	#[tokio::test]
+	async fn test_extension_method()
+	{
+		let mut router: Router<Request, Response> = Router::new(|_req: Request| async { "404".to_string() });
+
+		router.method_not_allowed(|_req: Request| async { "405".to_string() });
+
+		let purge = Method::from_bytes(b"PURGE").unwrap();
+
+		router.method(purge.clone(), "/cache/*path", r(test_handler_all));
+		router.get("/cache/*path", r(test_handler));
+
+		let handler = router.handle_req(&purge, "/cache/abc/def", &Request::default());
+
+		let res = handler
+			.handler
+			.invoke(Request::new(hyper::Body::from("")))
+			.await;
+
+		let res_body = res.into_body().next().await.unwrap().unwrap();
+
+		assert_eq!(res_body, "test_all");
+		assert_eq!("abc/def", handler.params.get("path").unwrap());
+
+		//the extension methods are also allowed methods
+		let handler = router.handle_req(&Method::POST, "/cache/abc", &Request::default());
+
+		assert_eq!(handler.allowed, vec![Method::GET, purge]);
+
+		//method without routes
+		let handler = router.handle_req(&Method::from_bytes(b"MKCOL").unwrap(), "/abc", &Request::default());
+
+		let res = handler
+			.handler
+			.invoke(Request::new(hyper::Body::from("")))
+			.await;
+
+		let res_body = res.into_body().next().await.unwrap().unwrap();
+
+		assert_eq!(res_body, "404");
+	}
+
+	#[tokio::test]
+	async fn test_auto_head()
+	{
+		let mut router: Router<Request, Response> = Router::new(|_req: Request| async { "404".to_string() });
+
+		router.method_not_allowed(|_req: Request| async { "405".to_string() });
+
+		router.get("/test", r(test_handler));
+		router.get("/test1", r(test_handler));
+		router.head("/test1", r(test_handler_all));
+
+		let handler = router.handle_req(&Method::HEAD, "/test", &Request::default());
+
+		assert!(!handler.allowed.is_empty());
+		assert!(!handler.strip_body);
+
+		router.auto_head(true);
+
+		let handler = router.handle_req(&Method::HEAD, "/test", &Request::default());
+
+		let res = handler
+			.handler
+			.invoke(Request::new(hyper::Body::from("")))
+			.await;
+
+		let res_body = res.into_body().next().await.unwrap().unwrap();
+
+		assert_eq!(res_body, "test");
+		assert!(handler.strip_body);
+
+		//explicit head route
+		let handler = router.handle_req(&Method::HEAD, "/test1", &Request::default());
+
+		let res = handler
+			.handler
+			.invoke(Request::new(hyper::Body::from("")))
+			.await;
+
+		let res_body = res.into_body().next().await.unwrap().unwrap();
+
+		assert_eq!(res_body, "test_all");
+		assert!(!handler.strip_body);
+
+		let handler = router.handle_req(&Method::POST, "/test", &Request::default());
+
+		assert_eq!(handler.allowed, vec![Method::GET, Method::HEAD]);
+	}
+
+	#[tokio::test]
+	async fn test_auto_options()
+	{
+		let mut router: Router<Request, Response> = Router::new(|_req: Request| async { "404".to_string() });
+
+		router.get("/test/:id", r(test_handler));
+		router.post("/test/:id", r(test_handler));
+		router.options("/test1", r(test_handler_all));
+		router.delete("/test1", r(test_handler_all));
+
+		let handler = router.handle_req(&Method::OPTIONS, "/test/abc", &Request::default());
+
+		assert!(handler.allowed.is_empty());
+
+		router.auto_options(true);
+
+		let handler = router.handle_req(&Method::OPTIONS, "/test/abc", &Request::default());
+
+		let res = handler
+			.handler
+			.invoke(Request::new(hyper::Body::from("")))
+			.await;
+
+		assert_eq!(res.status(), StatusCode::NO_CONTENT);
+		assert_eq!(handler.allowed, vec![Method::GET, Method::POST, Method::OPTIONS]);
+
+		//explicit options route
+		let handler = router.handle_req(&Method::OPTIONS, "/test1", &Request::default());
+
+		assert!(handler.allowed.is_empty());
+
+		let handler = router.handle_req(&Method::OPTIONS, "*", &Request::default());
+
+		assert_eq!(
+			handler.allowed,
+			vec![Method::GET, Method::POST, Method::DELETE, Method::OPTIONS]
+		);
+
+		//unknown path
+		let handler = router.handle_req(&Method::OPTIONS, "/abc", &Request::default());
+
+		let res = handler
+			.handler
+			.invoke(Request::new(hyper::Body::from("")))
+			.await;
+
+		let res_body = res.into_body().next().await.unwrap().unwrap();
+
+		assert_eq!(res_body, "404");
+	}
+
+	#[tokio::test]
+	async fn test_any_and_on()
+	{
+		let mut router: Router<Request, Response> = Router::new(|_req: Request| async { "404".to_string() });
 
-		fn call(&self, req: Request) -> impl Future<Output = Self::Output> + Send + 'static
-		{
-			let next = self.inner.clone();
+		router.method_not_allowed(|_req: Request| async { "405".to_string() });
 
-			async move {
-				//do something before req
+		router.any("/all/*a", r(test_handler_all).add(test_mw_transform));
+		router.on(&[Method::GET, Method::POST], "/test/:id", r(test_handler_param));
 
-				next.call(req).await
+		for method in &ROUTER_METHODS {
+			let handler = router.handle_req(method, "/all/abc", &Request::default());
 
-				//do something after
-			}
+			let res = handler
+				.handler
+				.invoke(Request::new(hyper::Body::from("")))
+				.await;
+
+			let res_body = res.into_body().next().await.unwrap().unwrap();
+
+			assert_eq!(res_body, "test_all");
 		}
+
+		//the same route instance for all methods
+		let get = router
+			.handle_req(&Method::GET, "/all/abc", &Request::default())
+			.handler as *const _ as *const ();
+		let put = router
+			.handle_req(&Method::PUT, "/all/abc", &Request::default())
+			.handler as *const _ as *const ();
+
+		assert_eq!(get, put);
+
+		let handler = router.handle_req(&Method::PUT, "/test/abc", &Request::default());
+
+		assert_eq!(handler.allowed, vec![Method::GET, Method::POST]);
 	}
 
-	fn test_mw_transform<S>(s: S) -> TestMw<S>
+	#[test]
+	fn test_url_for()
 	{
-		TestMw {
-			inner: Arc::new(s),
+		let mut router: Router<Request, Response> = Router::new(|_req: Request| async { "404".to_string() });
+
+		router.get("/users/:id", r(test_handler)).name("user.show");
+		router
+			.get("/files/:dir/*path", r(test_handler))
+			.name("file.show");
+
+		router.group("/admin", |g| {
+			g.on(&[Method::GET, Method::POST], "/user/:id/edit", r(test_handler))
+				.name("admin.user.edit");
+		});
+
+		let mut billing: Router<Request, Response> = Router::new(|_req: Request| async { "404".to_string() });
+
+		billing.get("/invoice/:id", r(test_handler)).name("invoice");
+
+		router.mount("/billing", billing);
+
+		assert_eq!(router.url_for("user.show", &[("id", "42")]).unwrap(), "/users/42");
+		assert_eq!(
+			router
+				.url_for("file.show", &[("dir", "a b/c"), ("path", "my doc/ä.txt")])
+				.unwrap(),
+			"/files/a%20b%2Fc/my%20doc/%C3%A4.txt"
+		);
+		assert_eq!(
+			router.url_for("admin.user.edit", &[("id", "1")]).unwrap(),
+			"/admin/user/1/edit"
+		);
+		assert_eq!(
+			router.url_for("invoice", &[("id", "1")]).unwrap(),
+			"/billing/invoice/1"
+		);
+
+		assert_eq!(
+			router.url_for("user.show", &[]),
+			Err(UrlError::MissingParam("id".to_string()))
+		);
+		assert_eq!(
+			router.url_for("user.show", &[("id", "42"), ("name", "abc")]),
+			Err(UrlError::ExtraParam("name".to_string()))
+		);
+		assert_eq!(
+			router.url_for("user", &[]),
+			Err(UrlError::UnknownRoute("user".to_string()))
+		);
+	}
+
+	#[test]
+	fn test_url_for_duplicate_name()
+	{
+		let mut router: Router<Request, Response> = Router::new(|_req: Request| async { "404".to_string() });
+
+		router.get("/a", r(test_handler)).name("n");
+		router.get("/b", r(test_handler)).name("n");
+
+		assert_eq!(router.url_for("n", &[]).unwrap(), "/b");
+		assert_eq!(
+			router.routes().collect::<Vec<_>>(),
+			vec![(Method::GET, "/a", None), (Method::GET, "/b", Some("n"))]
+		);
+
+		let handler = router.handle_req(&Method::GET, "/a", &Request::default());
+
+		assert!(handler.route.unwrap().name().is_none());
+
+		//a mounted route for many methods keeps the name for every method
+		let mut users: Router<Request, Response> = Router::new(|_req: Request| async { "404".to_string() });
+
+		users
+			.on(&[Method::GET, Method::POST], "/:id", r(test_handler))
+			.name("user");
+
+		router.mount("/users", users);
+
+		for method in [Method::GET, Method::POST] {
+			let handler = router.handle_req(&method, "/users/1", &Request::default());
+
+			assert_eq!(handler.route.unwrap().name(), Some("user"));
 		}
 	}
 
-	/**
-	Mw without arc
-	*/
-	pub struct TestMw1<S>
+	#[test]
+	fn test_routes()
 	{
-		inner: S,
+		let mut router: Router<Request, Response> = Router::new(|_req: Request| async { "404".to_string() });
+
+		router.post("/users", r(test_handler));
+		router.get("/users/:id", r(test_handler)).name("user.show");
+
+		router.group("/admin", |g| {
+			g.get("/users", r(test_handler));
+		});
+
+		router.method(Method::from_bytes(b"PURGE").unwrap(), "/cache", r(test_handler));
+
+		let routes = router.routes().collect::<Vec<_>>();
+
+		assert_eq!(
+			routes,
+			vec![
+				(Method::GET, "/users/:id", Some("user.show")),
+				(Method::GET, "/admin/users", None),
+				(Method::POST, "/users", None),
+				(Method::from_bytes(b"PURGE").unwrap(), "/cache", None),
+			]
+		);
 	}
 
-	impl<S> Service<Request> for TestMw1<S>
-	where
-		S: Service<Request, Output = Response>,
+	#[test]
+	fn test_params_parse()
 	{
-		type Output = S::Output;
+		let mut router: Router<Request, Response> = Router::new(|_req: Request| async { "404".to_string() });
 
-		fn call(&self, req: Request) -> impl Future<Output = Self::Output> + Send + 'static
+		router.get("/users/:id/:name", r(test_handler));
+
+		let handler = router.handle_req(&Method::GET, "/users/42/abc", &Request::default());
+
+		assert_eq!(handler.params.parse::<u64>("id"), Ok(42));
+		assert_eq!(
+			handler.params.parse::<u64>("user"),
+			Err(ParamError::Missing("user".to_string()))
+		);
+
+		let err = handler.params.parse::<u64>("name").unwrap_err();
+
+		assert!(matches!(err, ParamError::Invalid { ref param, .. } if param == "name"));
+
+		let res = err.into_response();
+
+		assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+	}
+
+	#[cfg(feature = "serde_params")]
+	#[test]
+	fn test_params_deserialize()
+	{
+		#[derive(serde::Deserialize, Debug, PartialEq)]
+		struct UserParams
 		{
-			let res = self.inner.call(req);
+			id: u64,
+			name: String,
+			admin: Option<bool>,
+		}
 
-			async move {
-				//do something before req
+		let mut router: Router<Request, Response> = Router::new(|_req: Request| async { "404".to_string() });
 
-				res.await
+		router.get("/users/:id/:name", r(test_handler));
+		router.get("/admin/:id/:name/:admin", r(test_handler));
 
-				//do something after
+		let handler = router.handle_req(&Method::GET, "/users/42/abc", &Request::default());
+
+		assert_eq!(
+			handler.params.deserialize::<UserParams>(),
+			Ok(UserParams {
+				id: 42,
+				name: "abc".to_string(),
+				admin: None,
+			})
+		);
+
+		let handler = router.handle_req(&Method::GET, "/admin/42/abc/true", &Request::default());
+
+		assert_eq!(handler.params.deserialize::<UserParams>().unwrap().admin, Some(true));
+
+		let handler = router.handle_req(&Method::GET, "/users/abc/abc", &Request::default());
+
+		assert!(matches!(
+			handler.params.deserialize::<UserParams>(),
+			Err(ParamError::Deserialize(_))
+		));
+	}
+
+	#[test]
+	fn test_params_order()
+	{
+		let mut router: Router<Request, Response> = Router::new(|_req: Request| async { "404".to_string() });
+
+		router.get("/users/:name/:id/*rest", r(test_handler));
+
+		let handler = router.handle_req(&Method::GET, "/users/abc/42/a/b", &Request::default());
+
+		assert_eq!(
+			handler.params.iter().collect::<Vec<_>>(),
+			vec![("name", "abc"), ("id", "42"), ("rest", "a/b")]
+		);
+		assert!(handler.params.contains_key("rest"));
+		assert!(!handler.params.contains_key("abc"));
+
+		let params: RouteParams = vec![("name", "abc"), ("id", "42"), ("rest", "a/b")]
+			.into_iter()
+			.collect();
+
+		assert_eq!(handler.params, params);
+	}
+
+	#[test]
+	fn test_decode_params()
+	{
+		let mut router: Router<Request, Response> = Router::new(|_req: Request| async { "404".to_string() });
+
+		router.get("/files/:file", r(test_handler));
+		router.get("/search/*name", r(test_handler));
+
+		let handler = router.handle_req(&Method::GET, "/files/my%20doc.txt", &Request::default());
+
+		assert_eq!(handler.params.get("file").unwrap(), "my doc.txt");
+
+		//encoded slash
+		let handler = router.handle_req(&Method::GET, "/files/a%2Fb", &Request::default());
+
+		assert_eq!(handler.params.get("file").unwrap(), "a/b");
+
+		let handler = router.handle_req(&Method::GET, "/search/%C3%A4/b%20c", &Request::default());
+
+		assert_eq!(handler.params.get("name").unwrap(), "ä/b c");
+		assert!(handler.param_error.is_none());
+
+		//not valid utf-8
+		let handler = router.handle_req(&Method::GET, "/files/%FF", &Request::default());
+
+		assert!(matches!(handler.param_error, Some(ParamError::Invalid { ref param, .. }) if param == "file"));
+
+		router.decode_params(false);
+
+		let handler = router.handle_req(&Method::GET, "/files/my%20doc.txt", &Request::default());
+
+		assert_eq!(handler.params.get("file").unwrap(), "my%20doc.txt");
+	}
+
+	#[test]
+	fn test_trailing_slash()
+	{
+		let mut router: Router<Request, Response> = Router::new(|_req: Request| async { "404".to_string() });
+
+		router.get("/users", r(test_handler));
+		router.get("/files/", r(test_handler));
+		router.get("/users/:id", r(test_handler));
+
+		let handler = router.handle_req(&Method::GET, "/users/", &Request::default());
+
+		assert!(handler.route.is_none());
+		assert!(handler.redirect.is_none());
+
+		router.trailing_slash(TrailingSlash::Redirect308);
+
+		let handler = router.handle_req(&Method::GET, "/users/", &Request::default());
+
+		assert_eq!(
+			handler.redirect,
+			Some((StatusCode::PERMANENT_REDIRECT, "/users".to_string()))
+		);
+
+		router.trailing_slash(TrailingSlash::Redirect301);
+
+		let handler = router.handle_req(&Method::GET, "/files", &Request::default());
+
+		assert_eq!(
+			handler.redirect,
+			Some((StatusCode::MOVED_PERMANENTLY, "/files/".to_string()))
+		);
+
+		//no route for the method
+		let handler = router.handle_req(&Method::POST, "/files", &Request::default());
+
+		assert!(handler.redirect.is_none());
+		assert!(handler.route.is_none());
+
+		router.trailing_slash(TrailingSlash::MatchBoth);
+
+		let handler = router.handle_req(&Method::GET, "/users/42/", &Request::default());
+
+		assert!(handler.redirect.is_none());
+		assert_eq!(handler.route.unwrap().pattern(), "/users/:id");
+		assert_eq!(handler.params.get("id").unwrap(), "42");
+	}
+
+	#[tokio::test]
+	async fn test_trailing_slash_allowed_methods()
+	{
+		let mut router: Router<Request, Response> = Router::new(|_req: Request| async { "404".to_string() });
+
+		router.method_not_allowed(|_req: Request| async { "405".to_string() });
+		router.auto_options(true);
+
+		router.get("/users", r(test_handler));
+		router.get("/files/", r(test_handler));
+
+		let handler = router.handle_req(&Method::POST, "/users/", &Request::default());
+
+		//strict: another path
+		assert!(handler.allowed.is_empty());
+
+		for trailing_slash in [TrailingSlash::Redirect301, TrailingSlash::Redirect308, TrailingSlash::MatchBoth] {
+			router.trailing_slash(trailing_slash);
+
+			for path in ["/users", "/users/", "/files", "/files/"] {
+				let handler = router.handle_req(&Method::POST, path, &Request::default());
+
+				let res = handler
+					.handler
+					.invoke(Request::new(hyper::Body::from("")))
+					.await;
+
+				let res_body = res.into_body().next().await.unwrap().unwrap();
+
+				assert_eq!(res_body, "405");
+				assert_eq!(handler.allowed, vec![Method::GET, Method::OPTIONS]);
+
+				let handler = router.handle_req(&Method::OPTIONS, path, &Request::default());
+
+				let res = handler
+					.handler
+					.invoke(Request::new(hyper::Body::from("")))
+					.await;
+
+				assert_eq!(res.status(), StatusCode::NO_CONTENT);
+				assert_eq!(handler.allowed, vec![Method::GET, Method::OPTIONS]);
 			}
 		}
 	}
 
-	fn test_mw_transform1<S>(s: S) -> TestMw1<S>
+	#[test]
+	fn test_trailing_slash_open_redirect()
 	{
-		TestMw1 {
-			inner: s,
+		let mut router: Router<Request, Response> = Router::new(|_req: Request| async { "404".to_string() });
+
+		router.trailing_slash(TrailingSlash::Redirect301);
+
+		router.get("/:a/:b", r(test_handler));
+
+		//the location would be the protocol relative url `//evil.com`
+		for path in ["//evil.com/", "/\\evil.com/"] {
+			let handler = router.handle_req(&Method::GET, path, &Request::default());
+
+			assert!(handler.redirect.is_none());
+			assert!(handler.route.is_none());
 		}
+
+		let handler = router.handle_req(&Method::GET, "/a/b/", &Request::default());
+
+		assert_eq!(
+			handler.redirect,
+			Some((StatusCode::MOVED_PERMANENTLY, "/a/b".to_string()))
+		);
 	}
 
-	#[tokio::test]
-	async fn test_adding_routes_and_match()
+	#[test]
+	fn test_normalize_path()
 	{
 		let mut router: Router<Request, Response> = Router::new(|_req: Request| async { "404".to_string() });
 
-		router.get("/test", r(test_handler).add(test_mw_transform));
+		router.get("/users/:id", r(test_handler));
+		router.get("/files/", r(test_handler));
 
-		router.post("/test/:id", r(test_handler_param).add(test_mw_transform1));
+		let handler = router.handle_req(&Method::GET, "//users/./abc/../42", &Request::default());
 
-		router.get("/test/all/*a", r(test_handler_all));
+		assert!(handler.route.is_none());
 
-		router.post("/test1/:id", r(test_handler_param).add(test_mw_transform1));
+		router.normalize_path(true);
 
-		//match all
-		let handler = router.handle_req(&Method::GET, "/test/all/abcdefg");
+		let handler = router.handle_req(&Method::GET, "//users/./abc/../42", &Request::default());
 
-		let res = handler
-			.handler
-			.invoke(Request::new(hyper::Body::from("")))
-			.await;
+		assert_eq!(handler.params.get("id").unwrap(), "42");
 
-		let res_body = res.into_body().next().await.unwrap().unwrap();
+		let handler = router.handle_req(&Method::GET, "/files//", &Request::default());
 
-		assert_eq!(res_body, "test_all");
-		assert_eq!("abcdefg", handler.params.get("a").unwrap());
+		assert!(handler.route.is_some());
 
-		//match with url param
-		let handler = router.handle_req(&Method::POST, "/test/abcdefg");
+		let handler = router.handle_req(&Method::GET, "/../../users/42", &Request::default());
 
-		let mut req = Request::new(hyper::Body::from(""));
-		req.extensions_mut().insert(handler.params);
+		assert!(handler.route.is_some());
+	}
 
-		let res = handler.handler.invoke(req).await;
+	#[test]
+	fn test_case_insensitive()
+	{
+		let mut router: Router<Request, Response> = Router::new(|_req: Request| async { "404".to_string() });
 
-		let res_body = res.into_body().next().await.unwrap().unwrap();
+		router.get("/api/users/:id", r(test_handler));
+		router.get("/api/Users/all", r(test_handler_all));
 
-		assert_eq!(res_body, "test_param: abcdefg");
+		let handler = router.handle_req(&Method::GET, "/API/Users/Abc", &Request::default());
+
+		assert!(handler.route.is_none());
+
+		router.case_insensitive(true);
+
+		//routes after enabling
+		router.get("/Files/*Path", r(test_handler));
+		router.method(Method::from_bytes(b"PURGE").unwrap(), "/Cache", r(test_handler));
+
+		let handler = router.handle_req(&Method::GET, "/API/Users/Abc", &Request::default());
+
+		assert_eq!(handler.route.unwrap().pattern(), "/api/users/:id");
+		assert_eq!(handler.params.get("id").unwrap(), "Abc");
+
+		//exact case first
+		let handler = router.handle_req(&Method::GET, "/api/Users/all", &Request::default());
+
+		assert_eq!(handler.route.unwrap().pattern(), "/api/Users/all");
+
+		let handler = router.handle_req(&Method::GET, "/files/My/Doc.txt", &Request::default());
+
+		assert_eq!(handler.params.get("Path").unwrap(), "My/Doc.txt");
+
+		let handler = router.handle_req(&Method::from_bytes(b"PURGE").unwrap(), "/cache", &Request::default());
+
+		assert!(handler.route.is_some());
 	}
 
-	#[tokio::test]
-	async fn test_result_handler()
+	#[test]
+	fn test_host()
 	{
 		let mut router: Router<Request, Response> = Router::new(|_req: Request| async { "404".to_string() });
 
-		router.get("/test", r(test_handler_result));
+		let mut api: Router<Request, Response> = Router::new(|_req: Request| async { "api 404".to_string() });
 
-		router.get("/test/:id", r(test_handler_result));
+		api.get("/users", r(test_handler));
+		let tenant: Router<Request, Response> = Router::new(|_req: Request| async { "tenant 404".to_string() });
+		let admin: Router<Request, Response> = Router::new(|_req: Request| async { "admin 404".to_string() });
 
-		router.get("/test/all/*a", r(test_handler_result));
+		router.host("api.example.com", api);
+		router.host("*.tenant.example.com", tenant);
+		router.host("admin.tenant.example.com", admin);
 
-		let handler = router.handle_req(&Method::GET, "/test/all/abcdefg");
+		assert_eq!(
+			router.hosts().map(|(host, _)| host).collect::<Vec<_>>(),
+			vec!["api.example.com", "*.tenant.example.com", "admin.tenant.example.com"]
+		);
+
+		//the routes of the host routers are only listed by the host router
+		assert_eq!(router.routes().count(), 0);
+		assert_eq!(
+			router
+				.hosts()
+				.flat_map(|(_, host_router)| host_router.routes())
+				.collect::<Vec<_>>(),
+			vec![(Method::GET, "/users", None)]
+		);
+
+		assert!(router.route_host("example.com").is_none());
+		assert!(router.route_host("tenant.example.com").is_none());
+
+		let (_, subdomain) = router.route_host("API.example.com").unwrap();
+
+		assert!(subdomain.is_none());
+
+		let (_, subdomain) = router.route_host("acme.Tenant.example.com").unwrap();
+
+		assert_eq!(subdomain, Some("acme"));
+
+		//exact host before the wildcard host
+		let (_, subdomain) = router.route_host("admin.tenant.example.com").unwrap();
+
+		assert!(subdomain.is_none());
+	}
+
+	#[test]
+	fn test_guards()
+	{
+		use crate::router::guard::{content_type, header};
+
+		let mut router: Router<Request, Response> = Router::new(|_req: Request| async { "404".to_string() });
+
+		router
+			.get("/users", r(|_req: Request| async { "v1".to_string() }))
+			.name("users.v1");
+		router
+			.get(
+				"/users",
+				r(|_req: Request| async { "v2".to_string() }).guard(header("Accept", "application/vnd.v2+json")),
+			)
+			.name("users.v2");
+		router.post(
+			"/upload",
+			r(|_req: Request| async { "upload".to_string() }).guard(content_type("multipart/form-data")),
+		);
+
+		//the route with guards is checked before the route without guards
+		let req = hyper::Request::builder()
+			.header("Accept", "text/html, application/vnd.v2+json;q=0.9")
+			.body(hyper::Body::empty())
+			.unwrap();
+
+		let handler = router.handle_req(&Method::GET, "/users", &req);
+
+		assert_eq!(handler.route.unwrap().name(), Some("users.v2"));
+
+		let handler = router.handle_req(&Method::GET, "/users", &Request::default());
+
+		assert_eq!(handler.route.unwrap().name(), Some("users.v1"));
+
+		let req = hyper::Request::builder()
+			.header("Content-Type", "multipart/form-data; boundary=abc")
+			.body(hyper::Body::empty())
+			.unwrap();
+
+		let handler = router.handle_req(&Method::POST, "/upload", &req);
+
+		assert!(handler.route.is_some());
+
+		let req = hyper::Request::builder()
+			.header("Content-Type", "application/json")
+			.body(hyper::Body::empty())
+			.unwrap();
+
+		let handler = router.handle_req(&Method::POST, "/upload", &req);
+
+		assert!(handler.route.is_none());
+		assert_eq!(handler.reject, Some(StatusCode::UNSUPPORTED_MEDIA_TYPE));
+	}
+
+	#[test]
+	fn test_guard_fall_through()
+	{
+		use crate::router::guard::{header, query};
+
+		let mut router: Router<Request, Response> = Router::new(|_req: Request| async { "404".to_string() });
+
+		router
+			.get(
+				"/export",
+				r(|_req: Request| async { "csv".to_string() }).guard(query("format", "csv file")),
+			)
+			.name("export.csv");
+		router
+			.get(
+				"/export",
+				r(|_req: Request| async { "xml".to_string() })
+					.guard(header("x-version", "2"))
+					.guard(|req: &Request| req.uri().query().is_some()),
+			)
+			.name("export.xml");
+
+		let req = hyper::Request::builder()
+			.uri("/export?format=csv%20file")
+			.header("x-version", "2")
+			.body(hyper::Body::empty())
+			.unwrap();
+
+		let handler = router.handle_req(&Method::GET, "/export", &req);
+
+		assert_eq!(handler.route.unwrap().name(), Some("export.csv"));
+
+		let req = hyper::Request::builder()
+			.uri("/export?format=xml")
+			.header("x-version", "2")
+			.body(hyper::Body::empty())
+			.unwrap();
+
+		let handler = router.handle_req(&Method::GET, "/export", &req);
+
+		assert_eq!(handler.route.unwrap().name(), Some("export.xml"));
+
+		//no route without guards: 404 handler
+		let req = hyper::Request::builder()
+			.uri("/export")
+			.header("x-version", "2")
+			.body(hyper::Body::empty())
+			.unwrap();
+
+		let handler = router.handle_req(&Method::GET, "/export", &req);
+
+		assert!(handler.route.is_none());
+		assert!(handler.reject.is_none());
+	}
+
+	#[tokio::test]
+	async fn test_guard_with_middleware()
+	{
+		use crate::router::guard::header;
+
+		let mut router: Router<Request, Response> = Router::new(|_req: Request| async { "404".to_string() });
+
+		router.get(
+			"/users",
+			r(test_handler)
+				.add(HeaderMwTransform("route"))
+				.guard(header("x-version", "2"))
+				.guard(header("x-client", "app")),
+		);
+
+		let req = hyper::Request::builder()
+			.header("x-version", "2")
+			.header("x-client", "app")
+			.body(hyper::Body::empty())
+			.unwrap();
+
+		let handler = router.handle_req(&Method::GET, "/users", &req);
 
 		let res = handler
 			.handler
 			.invoke(Request::new(hyper::Body::from("")))
 			.await;
 
-		let res_body = res.into_body().next().await.unwrap().unwrap();
+		assert_eq!(res.headers().get("x-mw").unwrap(), "route");
 
-		assert_eq!(res_body, "test");
-		assert_eq!("abcdefg", handler.params.get("a").unwrap());
+		//all guards must accept the request
+		let req = hyper::Request::builder()
+			.header("x-version", "2")
+			.body(hyper::Body::empty())
+			.unwrap();
+
+		let handler = router.handle_req(&Method::GET, "/users", &req);
+
+		assert!(handler.route.is_none());
+	}
+
+	#[test]
+	fn test_guard_conflict()
+	{
+		use crate::router::guard::header;
+
+		let mut router: Router<Request, Response> = Router::new(|_req: Request| async { "404".to_string() });
+
+		router.get("/users", r(|_req: Request| async { "v1".to_string() }));
+
+		assert!(router
+			.try_get(
+				"/users",
+				r(|_req: Request| async { "v2".to_string() }).guard(header("Accept", "application/vnd.v2+json")),
+			)
+			.is_ok());
+
+		//only one route without guards per path
+		assert!(router
+			.try_get("/users", r(|_req: Request| async { "v3".to_string() }))
+			.is_err());
+
+		//the guards are kept when the router is mounted
+		let mut app: Router<Request, Response> = Router::new(|_req: Request| async { "404".to_string() });
+
+		app.mount("/api", router);
+
+		let req = hyper::Request::builder()
+			.header("Accept", "application/json")
+			.body(hyper::Body::empty())
+			.unwrap();
+
+		assert!(app
+			.handle_req(&Method::GET, "/api/users", &req)
+			.route
+			.is_some());
+		assert_eq!(app.routes().count(), 2);
+	}
+
+	#[test]
+	fn test_guard_constructors()
+	{
+		use crate::router::guard::{content_type, header, query};
+
+		let req = hyper::Request::builder()
+			.uri("/export?page=2&format=csv%20file")
+			.header("Accept", "text/html, Application/Vnd.v2+json;q=0.9")
+			.header("Content-Type", "multipart/form-data; boundary=abc")
+			.body(())
+			.unwrap();
+
+		assert!(header("accept", "application/vnd.v2+json").check(&req));
+		assert!(!header("accept", "application/json").check(&req));
+		assert!(!header("x-test", "text/html").check(&req));
+
+		assert!(content_type("multipart/form-data").check(&req));
+		assert!(!content_type("application/json").check(&req));
+
+		assert!(query("format", "csv file").check(&req));
+		assert!(!query("format", "csv").check(&req));
+		assert!(!query("page", "3").check(&req));
 	}
 }
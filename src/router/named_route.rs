@@ -0,0 +1,103 @@
+use hyper::Method;
+use percent_encoding::{utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+
+use crate::router::route_error::UrlError;
+use crate::router::{RouteId, Router};
+
+/// encode everything except the unreserved chars of rfc 3986
+const SEGMENT: &AsciiSet = &NON_ALPHANUMERIC
+	.remove(b'-')
+	.remove(b'.')
+	.remove(b'_')
+	.remove(b'~');
+
+/// catch all params can contain more than one segment
+const CATCH_ALL: &AsciiSet = &SEGMENT.remove(b'/');
+
+/**
+# A saved route
+
+Returned by the router when saving a route, to give the route a name.
+
+````ignore
+router.get("/users/:id", r(user_handler)).name("user.show");
+
+let url = router.url_for("user.show", &[("id", "42")]).unwrap();
+
+assert_eq!(url, "/users/42");
+````
+*/
+pub struct RouteHandle<'a, Req, Res>
+where
+	Req: Send + Sync + 'static,
+	Res: Send + Sync + 'static,
+{
+	router: &'a mut Router<Req, Res>,
+	routes: Vec<(Method, RouteId)>,
+}
+
+impl<'a, Req, Res> RouteHandle<'a, Req, Res>
+where
+	Req: Send + Sync + 'static,
+	Res: Send + Sync + 'static,
+{
+	pub(crate) fn new(router: &'a mut Router<Req, Res>, routes: Vec<(Method, RouteId)>) -> Self
+	{
+		Self {
+			router,
+			routes,
+		}
+	}
+
+	/**
+	# Set the name of the route
+
+	The name is used to build the url of the route with the router url_for function.
+	When the name is already used by another route, the other route loses the name.
+	*/
+	pub fn name(self, name: &str)
+	{
+		self.router.name_routes(&self.routes, name);
+	}
+}
+
+/**
+Build the path from the matchit pattern and the params.
+
+The params are percent encoded. Catch all params keep their slashes.
+*/
+pub(crate) fn build_url(pattern: &str, params: &[(&str, &str)]) -> Result<String, UrlError>
+{
+	let mut url = String::with_capacity(pattern.len());
+	let mut used = Vec::with_capacity(params.len());
+	let mut rest = pattern;
+
+	while let Some(start) = rest.find([':', '*']) {
+		url.push_str(&rest[..start]);
+
+		let catch_all = rest.as_bytes()[start] == b'*';
+		let param = &rest[start + 1..];
+		let end = param.find('/').unwrap_or(param.len());
+		let key = &param[..end];
+
+		let value = match params.iter().find(|(k, _)| *k == key) {
+			Some((_, v)) => v,
+			None => return Err(UrlError::MissingParam(key.to_string())),
+		};
+
+		let set = if catch_all { CATCH_ALL } else { SEGMENT };
+
+		url.extend(utf8_percent_encode(value, set));
+		used.push(key);
+
+		rest = &param[end..];
+	}
+
+	url.push_str(rest);
+
+	if let Some((key, _)) = params.iter().find(|(k, _)| !used.contains(k)) {
+		return Err(UrlError::ExtraParam(key.to_string()));
+	}
+
+	Ok(url)
+}
@@ -0,0 +1,134 @@
+use serde::de::value::{Error, MapDeserializer};
+use serde::de::{Error as _, IntoDeserializer, Visitor};
+use serde::{forward_to_deserialize_any, Deserializer};
+
+/**
+# Deserialize the route params as map
+
+The keys are the param names.
+*/
+pub(crate) struct ParamsDeserializer<'a>
+{
+	params: &'a [(String, String)],
+}
+
+impl<'a> ParamsDeserializer<'a>
+{
+	pub(crate) fn new(params: &'a [(String, String)]) -> Self
+	{
+		Self {
+			params,
+		}
+	}
+}
+
+impl<'de, 'a> Deserializer<'de> for ParamsDeserializer<'a>
+{
+	type Error = Error;
+
+	fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+	where
+		V: Visitor<'de>,
+	{
+		let iter = self
+			.params
+			.iter()
+			.map(|(k, v)| (k.as_str(), ParamValue(v.as_str())));
+
+		visitor.visit_map(MapDeserializer::new(iter))
+	}
+
+	forward_to_deserialize_any! {
+		bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+		bytes byte_buf option unit unit_struct newtype_struct seq tuple
+		tuple_struct map struct enum identifier ignored_any
+	}
+}
+
+/**
+# Deserialize a single param value
+
+Simple types are parsed from the string.
+*/
+struct ParamValue<'a>(&'a str);
+
+impl<'a> IntoDeserializer<'_, Error> for ParamValue<'a>
+{
+	type Deserializer = Self;
+
+	fn into_deserializer(self) -> Self::Deserializer
+	{
+		self
+	}
+}
+
+macro_rules! deserialize_parse {
+	($($method:ident => $visit:ident,)*) => {
+		$(
+			fn $method<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+			where
+				V: Visitor<'de>,
+			{
+				match self.0.parse() {
+					Ok(v) => visitor.$visit(v),
+					Err(e) => Err(Error::custom(format!("{}: {}", e, self.0))),
+				}
+			}
+		)*
+	};
+}
+
+impl<'de, 'a> Deserializer<'de> for ParamValue<'a>
+{
+	type Error = Error;
+
+	fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+	where
+		V: Visitor<'de>,
+	{
+		visitor.visit_str(self.0)
+	}
+
+	deserialize_parse! {
+		deserialize_bool => visit_bool,
+		deserialize_i8 => visit_i8,
+		deserialize_i16 => visit_i16,
+		deserialize_i32 => visit_i32,
+		deserialize_i64 => visit_i64,
+		deserialize_i128 => visit_i128,
+		deserialize_u8 => visit_u8,
+		deserialize_u16 => visit_u16,
+		deserialize_u32 => visit_u32,
+		deserialize_u64 => visit_u64,
+		deserialize_u128 => visit_u128,
+		deserialize_f32 => visit_f32,
+		deserialize_f64 => visit_f64,
+		deserialize_char => visit_char,
+	}
+
+	fn deserialize_option<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+	where
+		V: Visitor<'de>,
+	{
+		visitor.visit_some(self)
+	}
+
+	fn deserialize_newtype_struct<V>(self, _name: &'static str, visitor: V) -> Result<V::Value, Self::Error>
+	where
+		V: Visitor<'de>,
+	{
+		visitor.visit_newtype_struct(self)
+	}
+
+	fn deserialize_enum<V>(self, _name: &'static str, _variants: &'static [&'static str], visitor: V) -> Result<V::Value, Self::Error>
+	where
+		V: Visitor<'de>,
+	{
+		visitor.visit_enum(self.0.into_deserializer())
+	}
+
+	forward_to_deserialize_any! {
+		str string bytes byte_buf unit unit_struct seq tuple
+		tuple_struct map struct identifier ignored_any
+	}
+}
@@ -0,0 +1,110 @@
+use std::borrow::Cow;
+
+/**
+# How to handle a request path with or without a trailing slash
+
+When the path is not matched but the path with (or without) the trailing slash got a route.
+
+Default is strict.
+*/
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TrailingSlash
+{
+	/// `/users` and `/users/` are different paths
+	#[default]
+	Strict,
+	/// redirect with `301 Moved Permanently` to the path of the route. Clients may change the method to get
+	Redirect301,
+	/// redirect with `308 Permanent Redirect` to the path of the route. Clients keep the method and the body
+	Redirect308,
+	/// call the route without a redirect
+	MatchBoth,
+}
+
+/**
+Add or remove the trailing slash.
+
+Returns None for the root path.
+*/
+pub(crate) fn toggle_trailing_slash(path: &str) -> Option<String>
+{
+	if path == "/" || !path.starts_with('/') {
+		return None;
+	}
+
+	match path.strip_suffix('/') {
+		Some(p) => Some(p.to_string()),
+		None => Some(path.to_string() + "/"),
+	}
+}
+
+/**
+Check if the path can be used as redirect location on the same host.
+
+Browsers follow `//host` and `/\host` to another host.
+*/
+pub(crate) fn is_local_path(path: &str) -> bool
+{
+	path.starts_with('/') && !path.starts_with("//") && !path.starts_with("/\\")
+}
+
+/**
+Remove duplicate slashes and resolve `.` and `..` segments.
+
+A `..` segment can't go above the root. The trailing slash is kept.
+*/
+pub(crate) fn normalize_path(path: &str) -> Cow<'_, str>
+{
+	if !path.starts_with('/') || (!path.contains("//") && !path.split('/').any(|s| s == "." || s == "..")) {
+		return Cow::Borrowed(path);
+	}
+
+	let mut segments = Vec::new();
+
+	for segment in path.split('/') {
+		match segment {
+			"" | "." => {},
+			".." => {
+				segments.pop();
+			},
+			s => segments.push(s),
+		}
+	}
+
+	let trailing_slash = path.ends_with('/') || path.ends_with("/.") || path.ends_with("/..");
+
+	let mut normalized = "/".to_string() + &segments.join("/");
+
+	if trailing_slash && !segments.is_empty() {
+		normalized.push('/');
+	}
+
+	Cow::Owned(normalized)
+}
+
+/**
+Lowercase the static segments of the route pattern.
+
+The param names are kept.
+*/
+pub(crate) fn lowercase_pattern(pattern: &str) -> String
+{
+	let mut lowercase = String::with_capacity(pattern.len());
+	let mut in_param = false;
+
+	for c in pattern.chars() {
+		match c {
+			':' | '*' => in_param = true,
+			'/' => in_param = false,
+			_ => {},
+		}
+
+		if in_param {
+			lowercase.push(c);
+		} else {
+			lowercase.push(c.to_ascii_lowercase());
+		}
+	}
+
+	lowercase
+}
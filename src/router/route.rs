@@ -1,8 +1,18 @@
-use std::collections::HashMap;
+use std::fmt::Display;
 use std::future::Future;
 use std::marker::PhantomData;
 use std::pin::Pin;
+use std::str::FromStr;
+use std::sync::Arc;
 
+use hyper::Method;
+use percent_encoding::percent_decode_str;
+
+use crate::router::guard::Guard;
+#[cfg(feature = "serde_params")]
+use crate::router::params_de::ParamsDeserializer;
+use crate::router::route_error::ParamError;
+use crate::router::DynRoute;
 use crate::service::{Service, ServiceTransform};
 
 pub(crate) trait Route<Req>: Send + Sync
@@ -13,6 +23,9 @@ pub(crate) trait Route<Req>: Send + Sync
 	fn invoke(&self, req: Req) -> Self::Future;
 }
 
+/// the guards of a route, all must accept the request
+type Guards<Req> = Vec<Arc<dyn Guard<Req>>>;
+
 //__________________________________________________________________________________________________
 
 pub struct GramRoute<S: 'static, Req, Res>
@@ -41,6 +54,11 @@ where
 		}
 	}
 
+	/**
+	# Apply a middleware to the route
+
+	Middleware must be added before the guards.
+	*/
 	pub fn add<S1, T, Req1, Res1>(self, middleware: T) -> GramRoute<S1, Req1, Res1>
 	where
 		T: ServiceTransform<S, Service = S1> + Send + Sync + 'static,
@@ -54,6 +72,37 @@ where
 			_res: Default::default(),
 		}
 	}
+
+	/**
+	# Add a guard to the route
+
+	The route is only matched when all guards accept the request.
+	Routes with guards can share the method and the path with other routes.
+	The routes are tried in the order they were saved, routes without guards last.
+
+	When no route accepted the request, the 404 handler is called
+	or `415 Unsupported Media Type` is returned when a content type guard failed.
+
+	Returns a GuardedRoute, so no middleware can be added after the guards.
+
+	````ignore
+	use rustgram::guard::{content_type, header};
+
+	router.get("/users", r(users_v2).add(mw_transform).guard(header("Accept", "application/vnd.v2+json")));
+	router.get("/users", r(users_v1));
+
+	router.post("/upload", r(upload_handler).guard(content_type("multipart/form-data")));
+	````
+	*/
+	pub fn guard<G>(self, guard: G) -> GuardedRoute<S, Req, Res>
+	where
+		G: Guard<Req> + 'static,
+	{
+		GuardedRoute {
+			route: self,
+			guards: vec![Arc::new(guard)],
+		}
+	}
 }
 
 impl<S, Req, Res> Route<Req> for GramRoute<S, Req, Res>
@@ -75,6 +124,117 @@ where
 
 //__________________________________________________________________________________________________
 
+/**
+# A route with guards
+
+Created by GramRoute::guard. More guards can be added, but no middleware.
+*/
+pub struct GuardedRoute<S: 'static, Req, Res>
+where
+	S: Service<Req, Output = Res>,
+	Req: Send + Sync,
+	Res: Send + Sync,
+{
+	route: GramRoute<S, Req, Res>,
+	guards: Guards<Req>,
+}
+
+impl<S, Req, Res> GuardedRoute<S, Req, Res>
+where
+	S: Service<Req, Output = Res>,
+	Req: Send + Sync,
+	Res: Send + Sync,
+{
+	/**
+	# Add another guard to the route
+
+	All guards must accept the request.
+	*/
+	pub fn guard<G>(mut self, guard: G) -> Self
+	where
+		G: Guard<Req> + 'static,
+	{
+		self.guards.push(Arc::new(guard));
+
+		self
+	}
+}
+
+/**
+# A route which can be saved in the router
+
+A GramRoute or a GuardedRoute.
+*/
+pub trait IntoRoute<Req, Res>
+where
+	Req: Send + Sync,
+	Res: Send + Sync,
+{
+	type Service: Service<Req, Output = Res> + 'static;
+
+	fn into_route(self) -> (GramRoute<Self::Service, Req, Res>, Guards<Req>);
+}
+
+impl<S, Req, Res> IntoRoute<Req, Res> for GramRoute<S, Req, Res>
+where
+	S: Service<Req, Output = Res> + 'static,
+	Req: Send + Sync,
+	Res: Send + Sync,
+{
+	type Service = S;
+
+	fn into_route(self) -> (GramRoute<S, Req, Res>, Guards<Req>)
+	{
+		(self, Vec::new())
+	}
+}
+
+impl<S, Req, Res> IntoRoute<Req, Res> for GuardedRoute<S, Req, Res>
+where
+	S: Service<Req, Output = Res> + 'static,
+	Req: Send + Sync,
+	Res: Send + Sync,
+{
+	type Service = S;
+
+	fn into_route(self) -> (GramRoute<S, Req, Res>, Guards<Req>)
+	{
+		(self.route, self.guards)
+	}
+}
+
+//__________________________________________________________________________________________________
+
+/**
+# An already saved route as service
+
+Used to apply middleware to the routes of a group after the routes were added to the router.
+*/
+pub struct RouteService<Req, Res>(Arc<DynRoute<Req, Res>>);
+
+impl<Req, Res> RouteService<Req, Res>
+{
+	pub(crate) fn new(route: Arc<DynRoute<Req, Res>>) -> Self
+	{
+		Self(route)
+	}
+}
+
+impl<Req, Res> Service<Req> for RouteService<Req, Res>
+where
+	Req: Send + Sync + 'static,
+	Res: Send + Sync + 'static,
+{
+	type Output = Res;
+
+	fn call(&self, req: Req) -> impl Future<Output = Self::Output> + Send + 'static
+	{
+		self.0.invoke(req)
+	}
+}
+
+//__________________________________________________________________________________________________
+
 /**
 # Returns a new GramRoute with the service
 */
@@ -90,18 +250,122 @@ where
 //__________________________________________________________________________________________________
 
 //from here: https://github.com/cloudflare/workers-rs/blob/d8b1149119ebf60fc0c2480cdf64996cfd152fac/worker/src/router.rs#L23
-pub struct RouteParams(HashMap<String, String>);
+/**
+# The params of the route path
+
+The params are in the same order as in the route path.
+
+Can be build from an iterator, e.g. for tests:
+
+```rust
+use rustgram::RouteParams;
+
+let params: RouteParams = vec![("id", "42"), ("name", "abc")].into_iter().collect();
+
+assert_eq!(params.len(), 2);
+assert_eq!(params.get("id").unwrap(), "42");
+assert_eq!(params.iter().next(), Some(("id", "42")));
+```
+*/
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct RouteParams(Vec<(String, String)>);
 
 impl RouteParams
 {
-	pub(crate) fn new() -> Self
+	pub fn new() -> Self
 	{
-		RouteParams(HashMap::new())
+		RouteParams(Vec::new())
 	}
 
 	pub fn get(&self, key: &str) -> Option<&String>
 	{
-		self.0.get(key)
+		self.0.iter().find(|(k, _)| k == key).map(|(_, v)| v)
+	}
+
+	pub fn contains_key(&self, key: &str) -> bool
+	{
+		self.0.iter().any(|(k, _)| k == key)
+	}
+
+	/**
+	# Iterate over the params
+
+	Returns the name and the value of the params in the order of the route path.
+	*/
+	pub fn iter(&self) -> impl Iterator<Item = (&str, &str)>
+	{
+		self.0.iter().map(|(k, v)| (k.as_str(), v.as_str()))
+	}
+
+	pub fn len(&self) -> usize
+	{
+		self.0.len()
+	}
+
+	pub fn is_empty(&self) -> bool
+	{
+		self.0.is_empty()
+	}
+
+	/**
+	# Get the param as type
+
+	Returns an error when the param is missing or can't be parsed.
+	The error is turned into a 400 response.
+
+	```rust
+	use rustgram::{ParamError, Request, RouteParams};
+
+	pub async fn test_handler(req: Request) -> Result<String, ParamError>
+	{
+		let params = req.extensions().get::<RouteParams>().unwrap();
+
+		let id = params.parse::<u64>("id")?;
+
+		Ok(format!("user: {}", id))
+	}
+	```
+	*/
+	pub fn parse<T>(&self, key: &str) -> Result<T, ParamError>
+	where
+		T: FromStr,
+		T::Err: Display,
+	{
+		let value = self
+			.get(key)
+			.ok_or_else(|| ParamError::Missing(key.to_string()))?;
+
+		value.parse::<T>().map_err(|e| {
+			ParamError::Invalid {
+				param: key.to_string(),
+				msg: e.to_string(),
+			}
+		})
+	}
+
+	/**
+	# Fill a struct from the params
+
+	Only with the feature `serde_params`.
+	Numbers, bools and other simple types are parsed from the param value.
+
+	````ignore
+	#[derive(Deserialize)]
+	struct UserParams
+	{
+		id: u64,
+		name: String,
+	}
+
+	let params = params.deserialize::<UserParams>()?;
+	````
+	*/
+	#[cfg(feature = "serde_params")]
+	pub fn deserialize<T>(&self) -> Result<T, ParamError>
+	where
+		T: serde::de::DeserializeOwned,
+	{
+		T::deserialize(ParamsDeserializer::new(&self.0)).map_err(|e| ParamError::Deserialize(e.to_string()))
 	}
 }
 
@@ -109,11 +373,108 @@ impl From<matchit::Params<'_, '_>> for RouteParams
 {
 	fn from(p: matchit::Params) -> Self
 	{
-		let mut route_params = RouteParams(HashMap::new());
-		for (ident, value) in p.iter() {
-			route_params.0.insert(ident.into(), value.into());
+		p.iter().collect()
+	}
+}
+
+impl RouteParams
+{
+	/**
+	Percent decode the params from the matched route.
+
+	Returns an error when a param is not valid utf-8 after decoding.
+	*/
+	pub(crate) fn decode<'a>(p: impl IntoIterator<Item = (&'a str, &'a str)>) -> Result<Self, ParamError>
+	{
+		p.into_iter()
+			.map(|(ident, value)| {
+				match percent_decode_str(value).decode_utf8() {
+					Ok(v) => Ok((ident, v)),
+					Err(e) => {
+						Err(ParamError::Invalid {
+							param: ident.to_string(),
+							msg: e.to_string(),
+						})
+					},
+				}
+			})
+			.collect()
+	}
+}
+
+impl<K, V> FromIterator<(K, V)> for RouteParams
+where
+	K: Into<String>,
+	V: Into<String>,
+{
+	fn from_iter<T: IntoIterator<Item = (K, V)>>(iter: T) -> Self
+	{
+		RouteParams(
+			iter.into_iter()
+				.map(|(k, v)| (k.into(), v.into()))
+				.collect(),
+		)
+	}
+}
+
+//__________________________________________________________________________________________________
+
+/**
+# The route which matched the request
+
+Inserted into the request extensions, like the RouteParams.
+Contains the path pattern of the route (e.g. `/users/:id`) instead of the request path,
+useful for metrics labels, tracing or audit logs.
+
+The pattern contains the prefix of groups and mounted routers.
+
+```rust
+use rustgram::{MatchedRoute, Request};
+
+pub async fn test_handler(req: Request) -> String
+{
+	let route = req.extensions().get::<MatchedRoute>().unwrap();
+
+	format!("{} {}", route.method(), route.pattern())
+}
+```
+*/
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MatchedRoute
+{
+	pattern: String,
+	method: Method,
+	name: Option<String>,
+}
+
+impl MatchedRoute
+{
+	pub(crate) fn new(pattern: String, method: Method, name: Option<String>) -> Self
+	{
+		Self {
+			pattern,
+			method,
+			name,
 		}
+	}
+
+	pub fn pattern(&self) -> &str
+	{
+		&self.pattern
+	}
 
-		route_params
+	/**
+	The method of the route.
+
+	For head requests which are handled by the get route, this is get.
+	*/
+	pub fn method(&self) -> &Method
+	{
+		&self.method
+	}
+
+	pub fn name(&self) -> Option<&str>
+	{
+		self.name.as_deref()
 	}
 }
@@ -0,0 +1,126 @@
+use std::error;
+use std::fmt::{Display, Formatter};
+
+use hyper::Method;
+use matchit::InsertError;
+
+/**
+# The reason why a route can't be saved
+*/
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RouteErrorKind
+{
+	/// The path conflicts with another route or is not a valid path
+	Insert(InsertError),
+}
+
+/**
+# Error when saving a route
+
+Returned by the try functions of the router, e.g. try_insert or try_get.
+
+Example:
+```rust
+use hyper::Method;
+use rustgram::{r, Request, RouteErrorKind, Router};
+
+async fn test_handler(_req: Request) -> String
+{
+	"test".to_string()
+}
+
+let mut router = Router::new(|_req: Request| async { "404".to_string() });
+
+router.try_get("/user/:id", r(test_handler)).unwrap();
+
+let err = router.try_get("/user/:name", r(test_handler)).err().unwrap();
+
+assert_eq!(err.method, Method::GET);
+assert_eq!(err.pattern, "/user/:name");
+assert!(matches!(err.kind, RouteErrorKind::Insert(_)));
+```
+*/
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RouteError
+{
+	pub method: Method,
+	/// the path of the route which can't be saved (incl. the prefix)
+	pub pattern: String,
+	pub kind: RouteErrorKind,
+}
+
+impl Display for RouteError
+{
+	fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result
+	{
+		match &self.kind {
+			RouteErrorKind::Insert(e) => write!(f, "Can't save the route {} {}: {}", self.method, self.pattern, e),
+		}
+	}
+}
+
+impl error::Error for RouteError {}
+
+/**
+# Error when building the url of a named route
+*/
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum UrlError
+{
+	/// No route with this name
+	UnknownRoute(String),
+	/// The param is in the path of the route but not in the given params
+	MissingParam(String),
+	/// The param is not in the path of the route
+	ExtraParam(String),
+}
+
+impl Display for UrlError
+{
+	fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result
+	{
+		match self {
+			UrlError::UnknownRoute(name) => write!(f, "No route with the name: {}", name),
+			UrlError::MissingParam(param) => write!(f, "Missing url param: {}", param),
+			UrlError::ExtraParam(param) => write!(f, "Url param is not in the route path: {}", param),
+		}
+	}
+}
+
+impl error::Error for UrlError {}
+
+/**
+# Error when reading a route param
+
+Turned into a 400 response, when returned by a handler.
+*/
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParamError
+{
+	/// The param is not in the route params
+	Missing(String),
+	/// The param value can't be parsed into the type
+	Invalid
+	{
+		param: String, msg: String
+	},
+	/// The params can't be deserialized into the struct (feature `serde_params`)
+	Deserialize(String),
+}
+
+impl Display for ParamError
+{
+	fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result
+	{
+		match self {
+			ParamError::Missing(param) => write!(f, "Missing route param: {}", param),
+			ParamError::Invalid {
+				param,
+				msg,
+			} => write!(f, "Invalid route param {}: {}", param, msg),
+			ParamError::Deserialize(msg) => write!(f, "Invalid route params: {}", msg),
+		}
+	}
+}
+
+impl error::Error for ParamError {}
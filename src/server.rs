@@ -0,0 +1,644 @@
+use std::convert::Infallible;
+use std::fmt::{Display, Formatter};
+use std::future::{pending, Future};
+use std::net::SocketAddr;
+#[cfg(unix)]
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::sync::Arc;
+use std::time::Duration;
+use std::{error, io};
+
+use hyper::server::accept::Accept;
+use hyper::server::conn::{AddrIncoming, AddrStream};
+use hyper::service::{make_service_fn, service_fn};
+use tokio::io::{AsyncRead, AsyncWrite};
+#[cfg(unix)]
+use tokio::net::UnixStream;
+use tokio::sync::watch;
+#[cfg(feature = "tls")]
+use tokio_rustls::server::TlsStream;
+
+use crate::router::route::RouteService;
+use crate::service::Service;
+#[cfg(feature = "tls")]
+use crate::tls::{TlsConfig, TlsIncoming};
+#[cfg(unix)]
+use crate::unix::{peer_credentials, PeerCredentials, UnixIncoming};
+use crate::{build_service, ReloadableRouter, Request, Response, Router};
+
+/// returns the service for the next request
+type App = Arc<dyn Fn() -> Arc<RouteService<Request, Response>> + Send + Sync>;
+
+type Signal = Pin<Box<dyn Future<Output = ()> + Send>>;
+
+/// hyper panics with a smaller max buffer size
+const MIN_BUF_SIZE: usize = 8192;
+
+/// connections which didn't finish the tls handshake in time are dropped
+#[cfg(feature = "tls")]
+const TLS_HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// the max number of tls handshakes at the same time
+#[cfg(feature = "tls")]
+const TLS_MAX_HANDSHAKES: usize = 1024;
+
+/**
+# Error of the server
+*/
+#[derive(Debug)]
+pub enum ServerError
+{
+	/// The address can't be bound, e.g. the port is already in use
+	Bind(hyper::Error),
+	/// The server stopped with an error
+	Serve(hyper::Error),
+	/// The unix socket can't be bound, the permissions can't be set or the listener can't be used
+	Socket(io::Error),
+	/// An option of the server is not valid, e.g. a too small max buffer size
+	Config(String),
+}
+
+impl Display for ServerError
+{
+	fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result
+	{
+		match self {
+			ServerError::Bind(e) => write!(f, "Can't bind the address: {}", e),
+			ServerError::Serve(e) => write!(f, "Server error: {}", e),
+			ServerError::Socket(e) => write!(f, "Socket error: {}", e),
+			ServerError::Config(e) => write!(f, "Invalid server option: {}", e),
+		}
+	}
+}
+
+impl error::Error for ServerError
+{
+	fn source(&self) -> Option<&(dyn error::Error + 'static)>
+	{
+		match self {
+			ServerError::Bind(e) | ServerError::Serve(e) => Some(e),
+			ServerError::Socket(e) => Some(e),
+			ServerError::Config(_) => None,
+		}
+	}
+}
+
+//__________________________________________________________________________________________________
+
+/**
+Where the server listens
+*/
+enum Listen
+{
+	Tcp(SocketAddr),
+	/// a listener which was bound before
+	StdListener(std::net::TcpListener),
+	Listener(tokio::net::TcpListener),
+	#[cfg(unix)]
+	Unix(PathBuf),
+}
+
+/**
+The bound listener
+*/
+enum Incoming
+{
+	Tcp(AddrIncoming),
+	#[cfg(unix)]
+	Unix(UnixIncoming),
+}
+
+/**
+Information about the connection, inserted into the request extensions
+*/
+#[derive(Clone, Default)]
+pub(crate) struct ConnInfo
+{
+	#[cfg(unix)]
+	peer: Option<PeerCredentials>,
+}
+
+impl ConnInfo
+{
+	fn insert(&self, _req: &mut Request)
+	{
+		#[cfg(unix)]
+		if let Some(peer) = self.peer {
+			_req.extensions_mut().insert(peer);
+		}
+	}
+}
+
+/**
+A connection of the listener
+*/
+pub(crate) trait Connection
+{
+	fn info(&self) -> ConnInfo
+	{
+		ConnInfo::default()
+	}
+}
+
+impl Connection for AddrStream {}
+
+#[cfg(feature = "tls")]
+impl Connection for TlsStream<AddrStream> {}
+
+#[cfg(unix)]
+impl Connection for UnixStream
+{
+	fn info(&self) -> ConnInfo
+	{
+		ConnInfo {
+			peer: peer_credentials(self),
+		}
+	}
+}
+
+/**
+Spawns the connections and the tls handshakes of the server.
+
+The connections are dropped when the drain timeout of the graceful shutdown is reached,
+so the open requests are cut off.
+*/
+#[derive(Clone)]
+pub(crate) struct ConnExecutor
+{
+	abort: watch::Receiver<bool>,
+}
+
+impl<F> hyper::rt::Executor<F> for ConnExecutor
+where
+	F: Future + Send + 'static,
+	F::Output: Send + 'static,
+{
+	fn execute(&self, fut: F)
+	{
+		let abort = self.abort.clone();
+
+		tokio::spawn(async move {
+			tokio::select! {
+				_ = fut => {},
+				_ = aborted(abort) => {},
+			}
+		});
+	}
+}
+
+async fn aborted(mut abort: watch::Receiver<bool>)
+{
+	while !*abort.borrow() {
+		if abort.changed().await.is_err() {
+			//the server stopped without the drain timeout, the connections keep running
+			pending::<()>().await;
+		}
+	}
+}
+
+//__________________________________________________________________________________________________
+
+/**
+# Build the http server
+
+Like start, but with the hyper options and errors instead of panics. Nothing is printed.
+
+````ignore
+let server = rustgram::Server::new(router, addr)
+	.http1_keep_alive(true)
+	.http1_header_read_timeout(Duration::from_secs(5))
+	.tcp_nodelay(true)
+	.bind()?;
+
+//the bound address, e.g. for port 0
+let addr = server.local_addr().unwrap();
+
+server.serve().await?;
+````
+*/
+pub struct Server
+{
+	app: App,
+	/// taken when the server is bound
+	listen: Option<Listen>,
+	keep_alive: bool,
+	header_read_timeout: Option<Duration>,
+	max_buf_size: Option<usize>,
+	tcp_nodelay: bool,
+	shutdown: Option<(Signal, Duration)>,
+	#[cfg(feature = "tls")]
+	tls: Option<TlsConfig>,
+	#[cfg(feature = "tls")]
+	tls_handshake_timeout: Duration,
+	#[cfg(feature = "tls")]
+	tls_max_handshakes: usize,
+	#[cfg(unix)]
+	unix_permissions: Option<u32>,
+}
+
+impl Server
+{
+	pub fn new(router: Router, addr: SocketAddr) -> Self
+	{
+		let service = Arc::new(build_service(router));
+
+		Self::with_app(Arc::new(move || service.clone()), Listen::Tcp(addr))
+	}
+
+	/**
+	# Build a server with a reloadable router
+	*/
+	pub fn reloadable(router: ReloadableRouter, addr: SocketAddr) -> Self
+	{
+		Self::with_app(Arc::new(move || router.current()), Listen::Tcp(addr))
+	}
+
+	/**
+	# Build a server which listens on a unix socket
+
+	Only on unix.
+
+	The socket file is removed when the server stopped.
+	A socket file of a crashed server (no one is listening) is replaced.
+	The handlers get the PeerCredentials of the client in the request extensions.
+
+	````ignore
+	rustgram::Server::unix(router, "/run/app/app.sock")
+		.unix_permissions(0o660)
+		.run()
+		.await?;
+	````
+	*/
+	#[cfg(unix)]
+	pub fn unix(router: Router, path: impl AsRef<Path>) -> Self
+	{
+		let service = Arc::new(build_service(router));
+
+		Self::with_app(
+			Arc::new(move || service.clone()),
+			Listen::Unix(path.as_ref().to_path_buf()),
+		)
+	}
+
+	/**
+	# Build a server with a listener which was bound before
+
+	E.g. to bind port 0 in tests or with a listener from listen_fds (systemd socket activation).
+
+	The listener is set to non blocking.
+	The server must be bound inside the tokio runtime.
+	*/
+	pub fn from_std_listener(router: Router, listener: std::net::TcpListener) -> Self
+	{
+		let service = Arc::new(build_service(router));
+
+		Self::with_app(Arc::new(move || service.clone()), Listen::StdListener(listener))
+	}
+
+	/**
+	# Build a server with a tokio listener which was bound before
+	*/
+	pub fn from_listener(router: Router, listener: tokio::net::TcpListener) -> Self
+	{
+		let service = Arc::new(build_service(router));
+
+		Self::with_app(Arc::new(move || service.clone()), Listen::Listener(listener))
+	}
+
+	fn with_app(app: App, listen: Listen) -> Self
+	{
+		Self {
+			app,
+			listen: Some(listen),
+			keep_alive: true,
+			header_read_timeout: None,
+			max_buf_size: None,
+			tcp_nodelay: false,
+			shutdown: None,
+			#[cfg(feature = "tls")]
+			tls: None,
+			#[cfg(feature = "tls")]
+			tls_handshake_timeout: TLS_HANDSHAKE_TIMEOUT,
+			#[cfg(feature = "tls")]
+			tls_max_handshakes: TLS_MAX_HANDSHAKES,
+			#[cfg(unix)]
+			unix_permissions: None,
+		}
+	}
+
+	/**
+	# Keep http 1 connections open for the next request
+
+	Enabled by default.
+	*/
+	pub fn http1_keep_alive(mut self, enable: bool) -> Self
+	{
+		self.keep_alive = enable;
+		self
+	}
+
+	/**
+	# Close the connection when the request headers are not received in time
+	*/
+	pub fn http1_header_read_timeout(mut self, timeout: Duration) -> Self
+	{
+		self.header_read_timeout = Some(timeout);
+		self
+	}
+
+	/**
+	# Set the max buffer size of a connection
+
+	Hyper default is around 400kb. Must be at least 8192 bytes, otherwise bind returns an error.
+	*/
+	pub fn http1_max_buf_size(mut self, size: usize) -> Self
+	{
+		self.max_buf_size = Some(size);
+		self
+	}
+
+	/**
+	# Set TCP_NODELAY on accepted connections
+
+	Disabled by default.
+	*/
+	pub fn tcp_nodelay(mut self, enable: bool) -> Self
+	{
+		self.tcp_nodelay = enable;
+		self
+	}
+
+	/**
+	# Stop the server when the signal is ready
+
+	Like start_with_shutdown: new connections are not accepted
+	and the open requests are finished, but not longer than the drain timeout.
+	After the timeout the open connections are closed.
+	*/
+	pub fn graceful_shutdown<F>(mut self, signal: F, drain_timeout: Duration) -> Self
+	where
+		F: Future<Output = ()> + Send + 'static,
+	{
+		self.shutdown = Some((Box::pin(signal), drain_timeout));
+		self
+	}
+
+	/**
+	# Set the permissions of the unix socket file
+
+	E.g. `0o660` for the user and the group of the server. Without this, the umask of the process is used.
+	The socket is bound in a private directory and only linked to the path after the permissions are set.
+	*/
+	#[cfg(unix)]
+	pub fn unix_permissions(mut self, mode: u32) -> Self
+	{
+		self.unix_permissions = Some(mode);
+		self
+	}
+
+	/**
+	# Serve https with the certificate
+
+	Only with the feature `tls`. Unix sockets are served without tls.
+	*/
+	#[cfg(feature = "tls")]
+	pub fn tls(mut self, config: TlsConfig) -> Self
+	{
+		self.tls = Some(config);
+		self
+	}
+
+	/**
+	# Drop connections which didn't finish the tls handshake in time
+
+	Default is 10 seconds. Only with the feature `tls`.
+	*/
+	#[cfg(feature = "tls")]
+	pub fn tls_handshake_timeout(mut self, timeout: Duration) -> Self
+	{
+		self.tls_handshake_timeout = timeout;
+		self
+	}
+
+	/**
+	# Set the max number of tls handshakes at the same time
+
+	When the max is reached, new connections wait until a handshake finished.
+	Default is 1024. Must be at least 1, otherwise bind returns an error. Only with the feature `tls`.
+	*/
+	#[cfg(feature = "tls")]
+	pub fn tls_max_handshakes(mut self, max: usize) -> Self
+	{
+		self.tls_max_handshakes = max;
+		self
+	}
+
+	/**
+	# Bind the address
+
+	Returns an error when the address can't be bound or an option is not valid.
+	*/
+	pub fn bind(mut self) -> Result<BoundServer, ServerError>
+	{
+		if let Some(size) = self.max_buf_size {
+			if size < MIN_BUF_SIZE {
+				return Err(ServerError::Config(format!(
+					"The max buffer size must be at least {} bytes, got {}",
+					MIN_BUF_SIZE, size
+				)));
+			}
+		}
+
+		#[cfg(feature = "tls")]
+		if self.tls_max_handshakes == 0 {
+			return Err(ServerError::Config(
+				"The max number of tls handshakes must be at least 1".to_string(),
+			));
+		}
+
+		//the server is only bound once, because bind takes the server
+		let listen = self.listen.take().unwrap();
+
+		let nodelay = self.tcp_nodelay;
+
+		let tcp = |mut incoming: AddrIncoming| {
+			incoming.set_nodelay(nodelay);
+
+			Incoming::Tcp(incoming)
+		};
+
+		let incoming = match listen {
+			Listen::Tcp(addr) => tcp(AddrIncoming::bind(&addr).map_err(ServerError::Bind)?),
+			Listen::StdListener(listener) => {
+				listener
+					.set_nonblocking(true)
+					.map_err(ServerError::Socket)?;
+
+				let listener = tokio::net::TcpListener::from_std(listener).map_err(ServerError::Socket)?;
+
+				tcp(AddrIncoming::from_listener(listener).map_err(ServerError::Bind)?)
+			},
+			Listen::Listener(listener) => tcp(AddrIncoming::from_listener(listener).map_err(ServerError::Bind)?),
+			#[cfg(unix)]
+			Listen::Unix(path) => Incoming::Unix(UnixIncoming::bind(&path, self.unix_permissions).map_err(ServerError::Socket)?),
+		};
+
+		Ok(BoundServer {
+			server: self,
+			incoming,
+		})
+	}
+
+	/**
+	# Bind the address and serve the router
+	*/
+	pub async fn run(self) -> Result<(), ServerError>
+	{
+		self.bind()?.serve().await
+	}
+}
+
+/**
+# A server with a bound address
+
+Returned by the bind function of the server.
+*/
+pub struct BoundServer
+{
+	server: Server,
+	incoming: Incoming,
+}
+
+impl BoundServer
+{
+	/**
+	The bound address, with the real port when the server was build with port 0.
+
+	None for unix sockets.
+	*/
+	pub fn local_addr(&self) -> Option<SocketAddr>
+	{
+		match &self.incoming {
+			Incoming::Tcp(incoming) => Some(incoming.local_addr()),
+			#[cfg(unix)]
+			Incoming::Unix(_) => None,
+		}
+	}
+
+	/**
+	# Serve the router
+
+	Returns when the server stopped after the shutdown signal or with an error.
+	*/
+	pub async fn serve(self) -> Result<(), ServerError>
+	{
+		let (abort_tx, abort_rx) = watch::channel(false);
+
+		let executor = ConnExecutor {
+			abort: abort_rx,
+		};
+
+		match self.incoming {
+			#[cfg(feature = "tls")]
+			Incoming::Tcp(incoming) if self.server.tls.is_some() => {
+				let server = self.server;
+
+				let incoming = TlsIncoming::new(
+					incoming,
+					server.tls.clone().unwrap(),
+					executor.clone(),
+					server.tls_handshake_timeout,
+					server.tls_max_handshakes,
+				);
+
+				serve_incoming(server, incoming, executor, abort_tx).await
+			},
+			Incoming::Tcp(incoming) => serve_incoming(self.server, incoming, executor, abort_tx).await,
+			#[cfg(unix)]
+			Incoming::Unix(incoming) => serve_incoming(self.server, incoming, executor, abort_tx).await,
+		}
+	}
+}
+
+/**
+Build the hyper server for the incoming connections and call the app service for every request.
+
+The connections are spawned with the executor, which drops them when the abort is sent after the drain timeout.
+*/
+async fn serve_incoming<I>(server: Server, incoming: I, executor: ConnExecutor, abort_tx: watch::Sender<bool>) -> Result<(), ServerError>
+where
+	I: Accept,
+	I::Conn: Connection + AsyncRead + AsyncWrite + Unpin + Send + 'static,
+	I::Error: Into<Box<dyn error::Error + Send + Sync>>,
+{
+	let app = server.app;
+
+	let new_service = make_service_fn(move |conn: &I::Conn| {
+		//this function will call for every connection
+		//init the app service
+		let app_capture = app.clone();
+		let info = conn.info();
+
+		async {
+			//return the result as async block
+			Ok::<_, Infallible>(service_fn(move |mut req| {
+				//this function will call for every request
+				info.insert(&mut req);
+
+				let service = app_capture();
+
+				async move { Ok::<_, Infallible>(service.call(req).await) }
+			}))
+		}
+	});
+
+	let mut builder = hyper::Server::builder(incoming)
+		.executor(executor)
+		.http1_keepalive(server.keep_alive);
+
+	if let Some(timeout) = server.header_read_timeout {
+		builder = builder.http1_header_read_timeout(timeout);
+	}
+
+	if let Some(size) = server.max_buf_size {
+		builder = builder.http1_max_buf_size(size);
+	}
+
+	let (signal, drain_timeout) = match server.shutdown {
+		Some((signal, drain_timeout)) => (signal, Some(drain_timeout)),
+		None => (Box::pin(pending()) as Signal, None),
+	};
+
+	let (drain_tx, drain_rx) = tokio::sync::oneshot::channel();
+
+	let server = builder
+		.serve(new_service)
+		.with_graceful_shutdown(async move {
+			signal.await;
+
+			let _ = drain_tx.send(());
+		});
+
+	let drain_timeout = match drain_timeout {
+		Some(t) => t,
+		None => return server.await.map_err(ServerError::Serve),
+	};
+
+	tokio::pin!(server);
+
+	tokio::select! {
+		res = &mut server => res.map_err(ServerError::Serve),
+		Ok(()) = drain_rx => {
+			//close the open connections after the timeout
+			match tokio::time::timeout(drain_timeout, server).await {
+				Ok(res) => res.map_err(ServerError::Serve),
+				Err(_) => {
+					let _ = abort_tx.send(true);
+
+					Ok(())
+				},
+			}
+		},
+	}
+}
@@ -2,6 +2,7 @@ use std::future::Future;
 
 use hyper::StatusCode;
 
+use crate::router::route_error::ParamError;
 use crate::service::gram_error::GramStdHttpErr;
 use crate::service::{IntoResponse, Service, ServiceTransform};
 use crate::{Request, Response};
@@ -94,6 +95,22 @@ where
 	}
 }
 
+impl IntoResponse<Response> for ParamError
+{
+	fn into_response(self) -> Response
+	{
+		handle_gram_err(self.into())
+	}
+}
+
+impl From<ParamError> for GramStdHttpErr
+{
+	fn from(e: ParamError) -> Self
+	{
+		GramStdHttpErr::new(400, e.to_string())
+	}
+}
+
 fn handle_gram_err(e: GramStdHttpErr) -> Response
 {
 	let status = StatusCode::from_u16(e.status).unwrap_or(StatusCode::BAD_REQUEST);
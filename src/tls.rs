@@ -0,0 +1,324 @@
+use std::fmt::{Display, Formatter};
+use std::future::Future;
+use std::path::Path;
+use std::pin::Pin;
+use std::sync::{Arc, RwLock};
+use std::task::{Context, Poll};
+use std::time::Duration;
+use std::{error, io};
+
+use hyper::rt::Executor;
+use hyper::server::accept::Accept;
+use hyper::server::conn::{AddrIncoming, AddrStream};
+use tokio::sync::{mpsc, AcquireError, OwnedSemaphorePermit, Semaphore};
+use tokio_rustls::rustls::{Certificate, PrivateKey, ServerConfig};
+use tokio_rustls::server::TlsStream;
+use tokio_rustls::{rustls, TlsAcceptor};
+
+use crate::server::ConnExecutor;
+
+/**
+# Error when loading the certificate or the key
+*/
+#[derive(Debug)]
+pub enum TlsError
+{
+	/// The file can't be read or the pem is not valid
+	Io(io::Error),
+	/// The pem contains no certificate
+	NoCertificate,
+	/// The pem contains no pkcs8, rsa or ec private key
+	NoPrivateKey,
+	/// The certificate or the key was rejected by rustls
+	Rustls(rustls::Error),
+}
+
+impl Display for TlsError
+{
+	fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result
+	{
+		match self {
+			TlsError::Io(e) => write!(f, "Can't read the pem: {}", e),
+			TlsError::NoCertificate => write!(f, "No certificate found in the pem"),
+			TlsError::NoPrivateKey => write!(f, "No private key found in the pem"),
+			TlsError::Rustls(e) => write!(f, "Invalid certificate or key: {}", e),
+		}
+	}
+}
+
+impl error::Error for TlsError
+{
+	fn source(&self) -> Option<&(dyn error::Error + 'static)>
+	{
+		match self {
+			TlsError::Io(e) => Some(e),
+			TlsError::Rustls(e) => Some(e),
+			_ => None,
+		}
+	}
+}
+
+//__________________________________________________________________________________________________
+
+/**
+# The certificate and the key of the https server
+
+Only with the feature `tls`.
+
+The certificate can be reloaded while the server runs (e.g. after a renewal).
+New connections use the new certificate, open connections are not changed.
+Cloning is cheap, all clones share the same certificate.
+
+The server announces `http/1.1` with alpn, and `h2` too when the feature `h2` is enabled.
+
+````ignore
+let tls = TlsConfig::from_pem_files("cert.pem", "key.pem")?;
+
+let server = rustgram::Server::new(router, addr).tls(tls.clone()).bind()?;
+
+//later
+tls.reload_from_pem_files("cert.pem", "key.pem")?;
+````
+*/
+#[derive(Clone)]
+pub struct TlsConfig
+{
+	config: Arc<RwLock<Arc<ServerConfig>>>,
+}
+
+impl TlsConfig
+{
+	/**
+	# Load the certificate chain and the private key from pem
+
+	The key can be a pkcs8, rsa or ec key.
+	*/
+	pub fn from_pem(cert_chain: &[u8], key: &[u8]) -> Result<Self, TlsError>
+	{
+		let config = server_config(cert_chain, key)?;
+
+		Ok(Self {
+			config: Arc::new(RwLock::new(Arc::new(config))),
+		})
+	}
+
+	/**
+	# Load the certificate chain and the private key from pem files
+	*/
+	pub fn from_pem_files(cert_chain: impl AsRef<Path>, key: impl AsRef<Path>) -> Result<Self, TlsError>
+	{
+		let (cert_chain, key) = read_files(cert_chain.as_ref(), key.as_ref())?;
+
+		Self::from_pem(&cert_chain, &key)
+	}
+
+	/**
+	# Replace the certificate
+
+	The old certificate is kept when the new one can't be loaded.
+	*/
+	pub fn reload_from_pem(&self, cert_chain: &[u8], key: &[u8]) -> Result<(), TlsError>
+	{
+		let config = server_config(cert_chain, key)?;
+
+		//the lock is only held to swap or clone the config
+		*self.config.write().unwrap() = Arc::new(config);
+
+		Ok(())
+	}
+
+	/**
+	# Replace the certificate from pem files
+	*/
+	pub fn reload_from_pem_files(&self, cert_chain: impl AsRef<Path>, key: impl AsRef<Path>) -> Result<(), TlsError>
+	{
+		let (cert_chain, key) = read_files(cert_chain.as_ref(), key.as_ref())?;
+
+		self.reload_from_pem(&cert_chain, &key)
+	}
+
+	/**
+	The config for the next connection
+	*/
+	pub(crate) fn current(&self) -> Arc<ServerConfig>
+	{
+		self.config.read().unwrap().clone()
+	}
+}
+
+fn read_files(cert_chain: &Path, key: &Path) -> Result<(Vec<u8>, Vec<u8>), TlsError>
+{
+	let cert_chain = std::fs::read(cert_chain).map_err(TlsError::Io)?;
+	let key = std::fs::read(key).map_err(TlsError::Io)?;
+
+	Ok((cert_chain, key))
+}
+
+fn server_config(cert_chain: &[u8], key: &[u8]) -> Result<ServerConfig, TlsError>
+{
+	let certs: Vec<Certificate> = rustls_pemfile::certs(&mut &*cert_chain)
+		.map_err(TlsError::Io)?
+		.into_iter()
+		.map(Certificate)
+		.collect();
+
+	if certs.is_empty() {
+		return Err(TlsError::NoCertificate);
+	}
+
+	let key = private_key(key)?;
+
+	let mut config = ServerConfig::builder()
+		.with_safe_defaults()
+		.with_no_client_auth()
+		.with_single_cert(certs, key)
+		.map_err(TlsError::Rustls)?;
+
+	config.alpn_protocols = alpn_protocols();
+
+	Ok(config)
+}
+
+/**
+The first pkcs8, rsa or ec key of the pem
+*/
+fn private_key(mut key: &[u8]) -> Result<PrivateKey, TlsError>
+{
+	loop {
+		match rustls_pemfile::read_one(&mut key).map_err(TlsError::Io)? {
+			Some(rustls_pemfile::Item::PKCS8Key(key)) | Some(rustls_pemfile::Item::RSAKey(key)) | Some(rustls_pemfile::Item::ECKey(key)) => {
+				return Ok(PrivateKey(key))
+			},
+			Some(_) => continue,
+			None => return Err(TlsError::NoPrivateKey),
+		}
+	}
+}
+
+fn alpn_protocols() -> Vec<Vec<u8>>
+{
+	let mut protocols = Vec::new();
+
+	if cfg!(feature = "h2") {
+		protocols.push(b"h2".to_vec());
+	}
+
+	protocols.push(b"http/1.1".to_vec());
+
+	protocols
+}
+
+//__________________________________________________________________________________________________
+
+/**
+# Accept tls connections
+
+The tcp connections are accepted by the hyper AddrIncoming.
+The tls handshakes run in own tasks of the server executor, so a slow client doesn't block other connections
+and the handshakes are dropped after the drain timeout like the connections.
+Connections with a failed handshake or which didn't finish the handshake in time are dropped.
+
+When the max number of handshakes is running, no new connections are accepted until a handshake finished.
+*/
+pub(crate) struct TlsIncoming
+{
+	incoming: AddrIncoming,
+	config: TlsConfig,
+	executor: ConnExecutor,
+	handshake_timeout: Duration,
+	handshakes: Arc<Semaphore>,
+	/// the permit for the next accepted connection
+	permit: Option<OwnedSemaphorePermit>,
+	acquire: Option<Acquire>,
+	tx: mpsc::UnboundedSender<TlsStream<AddrStream>>,
+	rx: mpsc::UnboundedReceiver<TlsStream<AddrStream>>,
+}
+
+type Acquire = Pin<Box<dyn Future<Output = Result<OwnedSemaphorePermit, AcquireError>> + Send>>;
+
+impl TlsIncoming
+{
+	pub(crate) fn new(incoming: AddrIncoming, config: TlsConfig, executor: ConnExecutor, handshake_timeout: Duration, max_handshakes: usize) -> Self
+	{
+		let (tx, rx) = mpsc::unbounded_channel();
+
+		Self {
+			incoming,
+			config,
+			executor,
+			handshake_timeout,
+			handshakes: Arc::new(Semaphore::new(max_handshakes)),
+			permit: None,
+			acquire: None,
+			tx,
+			rx,
+		}
+	}
+
+	fn poll_permit(&mut self, cx: &mut Context<'_>) -> Poll<OwnedSemaphorePermit>
+	{
+		if let Some(permit) = self.permit.take() {
+			return Poll::Ready(permit);
+		}
+
+		let handshakes = &self.handshakes;
+
+		let acquire = self
+			.acquire
+			.get_or_insert_with(|| Box::pin(handshakes.clone().acquire_owned()));
+
+		match acquire.as_mut().poll(cx) {
+			Poll::Ready(permit) => {
+				self.acquire = None;
+
+				//the semaphore is never closed
+				Poll::Ready(permit.unwrap())
+			},
+			Poll::Pending => Poll::Pending,
+		}
+	}
+}
+
+impl Accept for TlsIncoming
+{
+	type Conn = TlsStream<AddrStream>;
+	type Error = io::Error;
+
+	fn poll_accept(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Result<Self::Conn, Self::Error>>>
+	{
+		let this = self.get_mut();
+
+		while let Poll::Ready(permit) = this.poll_permit(cx) {
+			match Pin::new(&mut this.incoming).poll_accept(cx) {
+				Poll::Ready(Some(Ok(stream))) => {
+					let acceptor = TlsAcceptor::from(this.config.current());
+					let tx = this.tx.clone();
+					let timeout = this.handshake_timeout;
+
+					this.executor.execute(async move {
+						if let Ok(Ok(stream)) = tokio::time::timeout(timeout, acceptor.accept(stream)).await {
+							let _ = tx.send(stream);
+						}
+
+						//the next handshake can start
+						drop(permit);
+					});
+				},
+				Poll::Ready(Some(Err(e))) => {
+					this.permit = Some(permit);
+
+					return Poll::Ready(Some(Err(e)));
+				},
+				Poll::Ready(None) => return Poll::Ready(None),
+				Poll::Pending => {
+					this.permit = Some(permit);
+
+					break;
+				},
+			}
+		}
+
+		//the sender is never dropped, so the receiver always waits for the next connection
+		this.rx.poll_recv(cx).map(|stream| stream.map(Ok))
+	}
+}
@@ -0,0 +1,200 @@
+use std::fs::{DirBuilder, Permissions};
+use std::future::Future;
+use std::io;
+use std::os::unix::fs::{DirBuilderExt, FileTypeExt, PermissionsExt};
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::task::{ready, Context, Poll};
+use std::time::Duration;
+
+use hyper::server::accept::Accept;
+use tokio::net::{UnixListener, UnixStream};
+use tokio::time::Sleep;
+
+/**
+# The user and the group of the process on the other side of a unix socket
+
+Inserted into the request extensions, when the server listens on a unix socket.
+
+```rust
+use rustgram::{PeerCredentials, Request};
+
+pub async fn test_handler(req: Request) -> String
+{
+	match req.extensions().get::<PeerCredentials>() {
+		Some(peer) => format!("uid: {}, gid: {}", peer.uid, peer.gid),
+		None => "tcp".to_string(),
+	}
+}
+```
+*/
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PeerCredentials
+{
+	pub uid: u32,
+	pub gid: u32,
+	/// not available on every platform
+	pub pid: Option<i32>,
+}
+
+/// wait before accepting again after an error like too many open files
+const ACCEPT_ERROR_TIMEOUT: Duration = Duration::from_secs(1);
+
+/**
+# Accept connections on a unix socket
+
+The socket file is removed when the listener is dropped, e.g. after the graceful shutdown.
+
+Like the hyper AddrIncoming, errors of a single connection are skipped and after other errors
+(e.g. too many open files) the listener waits a second before accepting again.
+*/
+pub(crate) struct UnixIncoming
+{
+	listener: UnixListener,
+	path: PathBuf,
+	timeout: Option<Pin<Box<Sleep>>>,
+}
+
+impl UnixIncoming
+{
+	/**
+	Bind the socket and set the permissions of the socket file.
+
+	A socket file which is left from a crashed process (no one is listening) is removed before.
+	*/
+	pub(crate) fn bind(path: &Path, mode: Option<u32>) -> io::Result<Self>
+	{
+		remove_stale_socket(path)?;
+
+		let listener = match mode {
+			Some(mode) => bind_with_mode(path, mode)?,
+			None => UnixListener::bind(path)?,
+		};
+
+		Ok(Self {
+			listener,
+			path: path.to_path_buf(),
+			timeout: None,
+		})
+	}
+}
+
+/**
+Bind the socket in a private directory, set the permissions and then link the socket file to the path.
+
+So the socket can't be connected with the permissions of the umask before the permissions are set.
+Like bind, this fails when the path already exists.
+*/
+fn bind_with_mode(path: &Path, mode: u32) -> io::Result<UnixListener>
+{
+	let file_name = path
+		.file_name()
+		.ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "The socket path has no file name"))?;
+
+	let dir = path.with_file_name(format!(".{}.{}", file_name.to_string_lossy(), std::process::id()));
+
+	//a directory left from a crashed process with the same pid
+	if std::fs::symlink_metadata(&dir).is_ok() {
+		std::fs::remove_dir_all(&dir)?;
+	}
+
+	DirBuilder::new().mode(0o700).create(&dir)?;
+
+	let tmp_path = dir.join("socket");
+
+	let bind = || {
+		let listener = UnixListener::bind(&tmp_path)?;
+
+		std::fs::set_permissions(&tmp_path, Permissions::from_mode(mode))?;
+		std::fs::hard_link(&tmp_path, path)?;
+
+		Ok(listener)
+	};
+
+	let res = bind();
+
+	let _ = std::fs::remove_dir_all(&dir);
+
+	res
+}
+
+/**
+Remove the socket file when no server listens on it anymore (the connect is refused).
+
+Other files are kept, so bind fails for them.
+*/
+fn remove_stale_socket(path: &Path) -> io::Result<()>
+{
+	match std::fs::symlink_metadata(path) {
+		Ok(meta) if meta.file_type().is_socket() => {
+			match std::os::unix::net::UnixStream::connect(path) {
+				//another server still listens, bind fails with address in use
+				Ok(_) => Ok(()),
+				Err(e) if e.kind() == io::ErrorKind::ConnectionRefused => std::fs::remove_file(path),
+				Err(e) => Err(e),
+			}
+		},
+		_ => Ok(()),
+	}
+}
+
+impl Drop for UnixIncoming
+{
+	fn drop(&mut self)
+	{
+		let _ = std::fs::remove_file(&self.path);
+	}
+}
+
+impl Accept for UnixIncoming
+{
+	type Conn = UnixStream;
+	type Error = io::Error;
+
+	fn poll_accept(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Result<Self::Conn, Self::Error>>>
+	{
+		let this = self.get_mut();
+
+		if let Some(timeout) = &mut this.timeout {
+			ready!(timeout.as_mut().poll(cx));
+
+			this.timeout = None;
+		}
+
+		loop {
+			match ready!(this.listener.poll_accept(cx)) {
+				Ok((stream, _)) => return Poll::Ready(Some(Ok(stream))),
+				//the client closed the connection before it was accepted
+				Err(e) if is_connection_error(&e) => continue,
+				Err(_) => {
+					let mut timeout = Box::pin(tokio::time::sleep(ACCEPT_ERROR_TIMEOUT));
+
+					if timeout.as_mut().poll(cx).is_pending() {
+						this.timeout = Some(timeout);
+
+						return Poll::Pending;
+					}
+				},
+			}
+		}
+	}
+}
+
+fn is_connection_error(e: &io::Error) -> bool
+{
+	matches!(
+		e.kind(),
+		io::ErrorKind::ConnectionRefused | io::ErrorKind::ConnectionAborted | io::ErrorKind::ConnectionReset
+	)
+}
+
+pub(crate) fn peer_credentials(stream: &UnixStream) -> Option<PeerCredentials>
+{
+	let cred = stream.peer_cred().ok()?;
+
+	Some(PeerCredentials {
+		uid: cred.uid(),
+		gid: cred.gid(),
+		pid: cred.pid(),
+	})
+}